@@ -0,0 +1,226 @@
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+#[derive(Parser, Debug)]
+#[clap(name = "conver", author, version, about)]
+pub struct CmdArgs {
+    #[clap(subcommand)]
+    pub command: Option<Commands>,
+
+    /// Generate final release version (deprecated, use `conver next --release`)
+    #[clap(short, long, value_parser, default_value_t = false)]
+    pub release: bool,
+
+    /// Tag the current commit with the release version (deprecated, use `conver tag`)
+    #[clap(short, long, value_parser, default_value_t = false)]
+    pub tag: bool,
+
+    /// Add an optional leading v to the generated version i.e. (v2.1.3)
+    #[clap(long, value_parser, default_value_t = false)]
+    pub lead_v: bool,
+
+    /// Bump the version files with the derived version (deprecated, use `conver release`)
+    #[clap(short='f', long, value_parser, default_value_t = false)]
+    pub bump_files: bool,
+
+    /// Print release notes for the derived version (deprecated, use `conver changelog`)
+    #[clap(short='n', long, value_parser, default_value_t = false)]
+    pub notes: bool,
+
+    /// Print the file diffs, commit and tag without changing anything
+    #[clap(long, value_parser, default_value_t = false)]
+    pub dry_run: bool,
+
+    #[clap(flatten)]
+    pub output: OutputArgs,
+
+    /// Log more details to stderr, -v for info and -vv for debug events
+    #[clap(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Only log errors
+    #[clap(short, long, value_parser, default_value_t = false, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Path to target git repository
+    #[clap(value_parser, default_value_t = String::from("."))]
+    pub path: String
+}
+
+impl CmdArgs {
+    /// Whether any argument of the deprecated flag interface was given.
+    pub fn has_legacy_args(&self) -> bool {
+        self.release || self.tag || self.lead_v || self.bump_files || self.notes || self.dry_run
+            || self.output.output != OutputFormat::Text
+            || self.output.emit.is_some()
+            || self.output.emit_file.is_some()
+            || self.path != "."
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Print the most recent released version
+    Current(RepoArgs),
+    /// Print the next version derived from the commits since the last release
+    Next {
+        /// Generate final release version instead of a prerelease
+        #[clap(short, long, value_parser, default_value_t = false)]
+        release: bool,
+
+        #[clap(flatten)]
+        output: OutputArgs,
+
+        #[clap(flatten)]
+        repo: RepoArgs,
+    },
+    /// Bump the version and changelog files, commit them and tag the release
+    Release {
+        /// Print the file diffs, commit and tag without changing anything
+        #[clap(long, value_parser, default_value_t = false)]
+        dry_run: bool,
+
+        #[clap(flatten)]
+        output: OutputArgs,
+
+        #[clap(flatten)]
+        repo: RepoArgs,
+    },
+    /// Tag the current commit with the release version, without touching any file
+    Tag {
+        /// Tag the prerelease version instead of the final release version
+        #[clap(long, value_parser, default_value_t = false)]
+        prerelease: bool,
+
+        /// Print the tag that would be created without creating it
+        #[clap(long, value_parser, default_value_t = false)]
+        dry_run: bool,
+
+        #[clap(flatten)]
+        output: OutputArgs,
+
+        #[clap(flatten)]
+        repo: RepoArgs,
+    },
+    /// Record the release as a plan of file edits and git actions, to be applied later
+    Plan {
+        /// File the plan is written to [default: stdout]
+        #[clap(long, value_parser)]
+        out: Option<String>,
+
+        #[clap(flatten)]
+        repo: RepoArgs,
+    },
+    /// Apply a release plan, if HEAD and the planned files have not changed since
+    Apply {
+        /// Plan created by `conver plan`
+        #[clap(value_parser)]
+        plan: String,
+
+        /// Path to target git repository
+        #[clap(value_parser, default_value_t = String::from("."))]
+        path: String,
+    },
+    /// Print release notes for the commits since the last release
+    Changelog {
+        /// Title the notes with the prerelease version instead of the final release version
+        #[clap(long, value_parser, default_value_t = false)]
+        prerelease: bool,
+
+        #[clap(flatten)]
+        repo: RepoArgs,
+    },
+    /// Check that the commits since the last release follow conventional commits
+    Lint(RepoArgs),
+    /// Check that every version file contains the version of the latest release tag
+    Check {
+        /// Rewrite the mismatched version files, without committing them
+        #[clap(long, value_parser, default_value_t = false)]
+        fix: bool,
+
+        #[clap(flatten)]
+        repo: RepoArgs,
+    },
+    /// Write a conventional_release.toml for the repository
+    Init {
+        /// Overwrite an existing conventional_release.toml
+        #[clap(long, value_parser, default_value_t = false)]
+        force: bool,
+
+        #[clap(flatten)]
+        repo: RepoArgs,
+    },
+    /// Inspect the configuration loaded from conventional_release.toml
+    #[clap(subcommand)]
+    Config(ConfigCommands),
+    /// Print shell completions for conver
+    Completions {
+        #[clap(value_enum)]
+        shell: Shell,
+    },
+    /// Print the conver man page in roff format
+    Man,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Print the effective configuration after presets and defaults
+    Show {
+        /// Format of the printed configuration
+        #[clap(long, value_enum, default_value = "toml")]
+        format: ConfigFormat,
+    },
+    /// Report unknown keys, invalid regexes, missing files and invalid presets
+    Validate {
+        /// Path to target git repository, version files are resolved against it
+        #[clap(value_parser, default_value_t = String::from("."))]
+        path: String,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub struct RepoArgs {
+    /// Add an optional leading v to the generated version i.e. (v2.1.3)
+    #[clap(long, value_parser, default_value_t = false)]
+    pub lead_v: bool,
+
+    /// Path to target git repository
+    #[clap(value_parser, default_value_t = String::from("."))]
+    pub path: String,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct OutputArgs {
+    /// Output format
+    #[clap(short='o', long, value_enum, default_value = "text")]
+    pub output: OutputFormat,
+
+    /// Emit the derived version as CI outputs:
+    /// `github` appends to $GITHUB_OUTPUT, `gitlab` writes a dotenv artifact,
+    /// `dotenv` prints shell exports or writes --emit-file
+    #[clap(long, value_enum)]
+    pub emit: Option<EmitFormat>,
+
+    /// File written by --emit gitlab|dotenv [gitlab default: conver.env]
+    #[clap(long, value_parser)]
+    pub emit_file: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmitFormat {
+    Github,
+    Gitlab,
+    Dotenv,
+}
@@ -0,0 +1,342 @@
+use std::path::Path;
+
+use anyhow::Context;
+use conventional_semver_rs::{release, ConventionalRepo};
+use conventional_semver_rs::release::workspace;
+use conventional_semver_rs::release::plan::ReleasePlan;
+use conventional_semver_rs::config::{presets, validate, ConventionalSemverConfig, VersionFileConfig, WorkspaceConfig, CONFIG_PATH};
+
+use crate::cli::{ConfigFormat, EmitFormat, OutputArgs, OutputFormat, RepoArgs};
+use crate::dry_run::print_plan;
+use crate::output::{emit_outputs, JsonOutput};
+
+/// What a version derivation run is allowed to do.
+pub struct RunOptions {
+    pub release: bool,
+    pub lead_v: bool,
+    pub bump_files: bool,
+    pub tag: bool,
+    pub notes: bool,
+    pub dry_run: bool,
+    /// Fail when the repository is dirty or HEAD is tagged, instead of only printing the version.
+    pub require_release: bool,
+}
+
+/// Adds the optional leading v, if configured and not already present.
+fn display_version(repo: &ConventionalRepo, mut version: String, lead_v: bool) -> String {
+    if !version.starts_with(|begin: char| begin.eq_ignore_ascii_case(&'v'))
+        && (repo.config.v || lead_v) {
+        version.insert(0, 'v');
+    }
+    version
+}
+
+/// Derives the version of the repository at `path`, optionally bumping
+/// version files, committing and tagging the release.
+pub fn run(path: &str, opts: &RunOptions, output: &OutputArgs) -> anyhow::Result<()> {
+    let repo = ConventionalRepo::new(path)?;
+
+    let details = repo.derive_version_details(opts.release)?;
+    if opts.require_release && !opts.dry_run {
+        if details.dirty {
+            anyhow::bail!("nothing was released, the repository is dirty, commit or stash the changes first");
+        }
+        if details.tagged {
+            anyhow::bail!("nothing was released, HEAD is already tagged");
+        }
+    }
+    let version = display_version(&repo, details.version.clone(), opts.lead_v);
+    let notes = match opts.notes {
+        true => Some(repo.release_notes(&version)?.render_markdown()),
+        false => None,
+    };
+    // Shell exports on stdout must not be mixed with the version.
+    let stdout_exports = output.emit == Some(EmitFormat::Dotenv) && output.emit_file.is_none();
+    if output.output == OutputFormat::Text && !stdout_exports {
+        println!("{}", version);
+        if let Some(notes) = &notes {
+            println!("\n{}", notes);
+        }
+    }
+
+    let dirty = details.dirty;
+    let tagged_head = details.tagged;
+    let mut release_errors = vec![];
+    let mut commit_oid = None;
+    let mut tag_oid = None;
+    if opts.dry_run {
+        // Keep stdout parseable when printing JSON.
+        release_errors = match output.output {
+            OutputFormat::Text => print_plan(&mut std::io::stdout(), &repo, path, &version, &details, opts)?,
+            OutputFormat::Json => print_plan(&mut std::io::stderr(), &repo, path, &version, &details, opts)?,
+        };
+    } else if opts.bump_files && !dirty && !tagged_head {
+        // Every file is computed before any is written, a single error leaves the repository untouched.
+        let (updates, errors) = release::compute_release_files(&repo, path, &version, &details.head.to_string())?;
+        release_errors = errors;
+        if release_errors.is_empty() {
+            // A failed write, commit or tag restores every file.
+            let tag = opts.tag.then_some(version.as_str());
+            match release::release_file_updates(&repo, path, &updates, &release::release_commit_message(&version), tag) {
+                Ok((commit, tag)) => {
+                    commit_oid = Some(commit);
+                    tag_oid = tag;
+                },
+                Err(e) => release_errors.push(e),
+            }
+        }
+        if !release_errors.is_empty() && output.output == OutputFormat::Text {
+            release_errors.iter().for_each(|e| {
+                tracing::error!("{}", e);
+            });
+        }
+    }
+    if opts.tag && !opts.bump_files && !opts.dry_run && !dirty && !tagged_head && release_errors.is_empty() {
+        tag_oid = Some(release::tag_release(&repo, &version)?);
+    }
+
+    if let Some(emit) = output.emit {
+        // A dirty tagged HEAD derives a new prerelease, which is not the tag.
+        let released = (tagged_head && !dirty) || tag_oid.is_some();
+        let outputs = [
+            ("version", version.clone()),
+            ("previous_version", details.previous_version.clone().unwrap_or_default()),
+            ("bump", details.bump.to_string()),
+            ("released", released.to_string()),
+            ("tag", match released {
+                true => version.clone(),
+                false => String::new(),
+            }),
+        ];
+        emit_outputs(emit, output.emit_file.as_deref(), &outputs)?;
+    }
+
+    if output.output == OutputFormat::Json {
+        let output = JsonOutput {
+            previous_version: details.previous_version,
+            version,
+            bump: details.bump.to_string(),
+            commit_count: details.commit_count,
+            head: details.head.to_string(),
+            dirty,
+            tagged: tagged_head,
+            commit_created: commit_oid.is_some(),
+            commit_oid: commit_oid.map(|oid| oid.to_string()),
+            tag_created: tag_oid.is_some(),
+            tag_oid: tag_oid.map(|oid| oid.to_string()),
+            version_file_errors: release_errors.iter().map(|e| e.to_string()).collect(),
+            notes,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
+    if !release_errors.is_empty() {
+        anyhow::bail!("{} version file error(s), no file was changed and nothing was committed or tagged", release_errors.len());
+    }
+    Ok(())
+}
+
+/// Prints the most recent released version.
+pub fn current(args: &RepoArgs) -> anyhow::Result<()> {
+    let repo = ConventionalRepo::new(&args.path)?;
+    let version = repo.get_current_version()?
+        .ok_or_else(|| anyhow::anyhow!("No release tag is reachable from HEAD"))?;
+    println!("{}", display_version(&repo, version, args.lead_v));
+    Ok(())
+}
+
+/// Records the release of HEAD as a plan for `apply`.
+pub fn plan(out: Option<&str>, args: &RepoArgs) -> anyhow::Result<()> {
+    let repo = ConventionalRepo::new(&args.path)?;
+    let details = repo.derive_version_details(true)?;
+    let version = display_version(&repo, details.version.clone(), args.lead_v);
+    let plan = ReleasePlan::create(&repo, &args.path, &details, &version)?;
+    let json = serde_json::to_string_pretty(&plan)?;
+    match out {
+        Some(out) => {
+            std::fs::write(out, json + "\n")?;
+            println!("{}", version);
+        },
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// Applies a plan recorded by `plan`.
+pub fn apply(plan: &str, path: &str) -> anyhow::Result<()> {
+    let plan: ReleasePlan = serde_json::from_str(&std::fs::read_to_string(plan)?)?;
+    let repo = ConventionalRepo::new(path)?;
+    plan.apply(&repo, path)?;
+    println!("{}", plan.version);
+    Ok(())
+}
+
+/// Prints the release notes of the next, or currently tagged, release.
+pub fn changelog(prerelease: bool, args: &RepoArgs) -> anyhow::Result<()> {
+    let repo = ConventionalRepo::new(&args.path)?;
+    let version = display_version(&repo, repo.derive_version(!prerelease)?, args.lead_v);
+    print!("{}", repo.release_notes(&version)?.render_markdown());
+    Ok(())
+}
+
+/// Reports every commit since the last release that is not a conventional commit.
+pub fn lint(args: &RepoArgs) -> anyhow::Result<()> {
+    let repo = ConventionalRepo::new(&args.path)?;
+    let failures = repo.lint_commits()?;
+    for f in &failures {
+        println!("{} {}: {}", &f.id.to_string()[..7], f.summary, f.reason);
+    }
+    if !failures.is_empty() {
+        anyhow::bail!("{} commit(s) do not follow conventional commits", failures.len());
+    }
+    Ok(())
+}
+
+/// Compares every version file with the latest release tag, printing a table of the mismatches.
+/// With `fix`, the mismatched files are rewritten but not committed.
+pub fn check(fix: bool, args: &RepoArgs) -> anyhow::Result<()> {
+    let repo = ConventionalRepo::new(&args.path)?;
+    let version = repo.get_current_version()?
+        .ok_or_else(|| anyhow::anyhow!("No release tag is reachable from HEAD"))?;
+    let v_files = release::VersionFile::config_to_version_files(&repo.config, &args.path)?;
+    let (mismatches, errors) = release::check_version_files(&args.path, &version, &v_files);
+    for e in &errors {
+        tracing::error!("{}", e);
+    }
+    if mismatches.is_empty() && errors.is_empty() {
+        println!("All {} version file(s) match {}", v_files.len(), version);
+        return Ok(());
+    }
+
+    if !mismatches.is_empty() {
+        // One row per differing line, so a workspace requirement shows why a file with the right version mismatches.
+        let rows: Vec<(String, &str, &str)> = mismatches.iter()
+            .flat_map(|m| m.lines.iter().map(move |l| (format!("{}:{}", m.relative_path, l.line), l.found.as_str(), l.expected.as_str())))
+            .collect();
+        let path_width = rows.iter().map(|r| r.0.len()).chain([4]).max().unwrap_or_default();
+        let found_width = rows.iter().map(|r| r.1.len()).chain([5]).max().unwrap_or_default();
+        println!("{:path_width$}  {:found_width$}  EXPECTED", "FILE", "FOUND");
+        for (path, found, expected) in rows {
+            println!("{:path_width$}  {:found_width$}  {}", path, found, expected);
+        }
+    }
+    if fix && !mismatches.is_empty() {
+        let (updates, _) = release::compute_version_files(&args.path, &version, &v_files);
+        let changed: Vec<release::FileUpdate> = updates.into_iter().filter(|u| u.before != u.after).collect();
+        release::write_file_updates(&args.path, &changed)?;
+        println!("Fixed {} file(s), review and commit them", changed.len());
+        if errors.is_empty() {
+            return Ok(());
+        }
+    }
+    match errors.is_empty() {
+        true => anyhow::bail!("{} version file(s) do not match {}", mismatches.len(), version),
+        false => anyhow::bail!("{} version file(s) do not match {}, {} could not be checked",
+            mismatches.len(), version, errors.len()),
+    }
+}
+
+/// Writes a conventional_release.toml for the repository, with a version file
+/// for every known manifest and the version style of the existing tags.
+pub fn init(force: bool, args: &RepoArgs) -> anyhow::Result<()> {
+    let pth = Path::new(&args.path).join(CONFIG_PATH);
+    if pth.exists() && !force {
+        anyhow::bail!("{} already exists, use --force to overwrite it", pth.display());
+    }
+
+    // Workspace roots are bumped along with their members and lockfiles, instead of the manifest preset.
+    let cargo_workspace = workspace::cargo::discover(&args.path, ".").is_ok();
+    let npm_workspace = workspace::npm::discover(&args.path, ".").is_ok_and(|w| {
+        !w.member_dirs.is_empty() || w.package_lock.is_some() || w.pnpm_lock.is_some()
+    });
+    let detected: Vec<&VersionFileConfig> = presets::detect_presets(&args.path).into_iter()
+        .filter(|p| !(cargo_workspace && p.path == "Cargo.toml"))
+        .filter(|p| !(npm_workspace && p.path == "package.json"))
+        .collect();
+    let repo = ConventionalRepo::new(&args.path)?;
+    // Repositories without any commit have no tags either.
+    let latest_tag = repo.get_current_version().ok().flatten();
+    let v = args.lead_v || latest_tag.as_ref()
+        .is_some_and(|t| t.starts_with(|c: char| c.eq_ignore_ascii_case(&'v')));
+    let initial_version = match &latest_tag {
+        Some(tag) => Some(tag.trim_start_matches(|c: char| c.eq_ignore_ascii_case(&'v')).to_string()),
+        None => detected.iter().find_map(|p| {
+            release::VersionFile::new(
+                p.path.clone(),
+                p.key.clone(),
+                p.version_prefix.clone().unwrap_or_default(),
+                p.version_postfix.clone().unwrap_or_default(),
+                p.v.unwrap_or(false),
+            ).ok()?.read_version(&args.path).ok()
+        }),
+    };
+
+    let version_files: Vec<VersionFileConfig> = detected.iter().map(|p| VersionFileConfig{
+        v: None,
+        path: String::new(),
+        allow_no_matches: None,
+        key: None,
+        version_prefix: None,
+        version_postfix: None,
+        literal: None,
+        occurrences: None,
+        version_scheme: None,
+        preset: p.preset.clone(),
+    }).collect();
+    for p in &detected {
+        match p.preset.as_deref() {
+            Some(preset) if preset != p.path => println!("Detected {} ({})", p.path, preset),
+            _ => println!("Detected {}", p.path),
+        }
+    }
+    let mut workspaces = vec![];
+    for (ecosystem, found) in [("cargo", cargo_workspace), ("npm", npm_workspace)] {
+        if found {
+            println!("Detected {} workspace", ecosystem);
+            workspaces.push(WorkspaceConfig{
+                ecosystem: String::from(ecosystem),
+                path: String::from("."),
+            });
+        }
+    }
+    let config = ConventionalSemverConfig{
+        v,
+        initial_version,
+        version_files: match version_files.is_empty() {
+            true => None,
+            false => Some(version_files),
+        },
+        workspaces: match workspaces.is_empty() {
+            true => None,
+            false => Some(workspaces),
+        },
+        ..Default::default()
+    };
+    std::fs::write(&pth, toml::to_string(&config)?)?;
+    println!("Wrote {}", pth.display());
+    Ok(())
+}
+
+/// Prints the effective configuration, after presets and defaults.
+pub fn config_show(format: ConfigFormat) -> anyhow::Result<()> {
+    let config = ConventionalSemverConfig::load_config()?;
+    match format {
+        ConfigFormat::Toml => print!("{}", toml::to_string(&config)?),
+        ConfigFormat::Json => println!("{}", serde_json::to_string_pretty(&config)?),
+    }
+    Ok(())
+}
+
+/// Reports every problem found in conventional_release.toml.
+pub fn config_validate(path: &str) -> anyhow::Result<()> {
+    let source = std::fs::read_to_string(CONFIG_PATH)
+        .with_context(|| format!("Failed to read {}", CONFIG_PATH))?;
+    let diagnostics = validate::validate(&source, path);
+    for d in &diagnostics {
+        println!("{}:{}", CONFIG_PATH, d);
+    }
+    if !diagnostics.is_empty() {
+        anyhow::bail!("{} problem(s) found in {}", diagnostics.len(), CONFIG_PATH);
+    }
+    println!("{} is valid", CONFIG_PATH);
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::io::Write;
+
+use conventional_semver_rs::{release, ConventionalRepo, VersionDetails};
+use similar::TextDiff;
+
+use crate::commands::RunOptions;
+
+/// Prints what a release run would change, without touching the worktree, index or refs.
+/// Version files are bumped in memory and shown as unified diffs,
+/// followed by the release commit and tag that would be created.
+/// The version file errors are returned, nothing would be committed or tagged when there is any.
+pub fn print_plan(
+    out: &mut dyn Write,
+    repo: &ConventionalRepo,
+    path: &str,
+    version: &str,
+    details: &VersionDetails,
+    opts: &RunOptions,
+) -> anyhow::Result<Vec<release::Error>> {
+    if details.dirty {
+        writeln!(out, "Nothing would be released, the repository is dirty")?;
+        return Ok(vec![]);
+    }
+    if details.tagged {
+        writeln!(out, "Nothing would be released, HEAD is already tagged")?;
+        return Ok(vec![]);
+    }
+
+    let sig = release::release_signature(repo)?;
+    let signature = format!("{} <{}>",
+        sig.name().unwrap_or_default(),
+        sig.email().unwrap_or_default());
+    if opts.bump_files {
+        let (updates, errors) = release::compute_release_files(repo, path, version, &details.head.to_string())?;
+        for u in &updates {
+            let diff = TextDiff::from_lines(&u.before, &u.after);
+            write!(out, "{}", diff.unified_diff()
+                .header(&format!("a/{}", u.relative_path), &format!("b/{}", u.relative_path)))?;
+        }
+        for e in &errors {
+            writeln!(out, "error: {}", e)?;
+        }
+        if !errors.is_empty() {
+            writeln!(out, "\nNothing would be committed or tagged, {} version file error(s)", errors.len())?;
+            return Ok(errors);
+        }
+
+        writeln!(out, "\nWould commit {} file(s)", updates.len())?;
+        writeln!(out, "    message: {}", release::release_commit_message(version))?;
+        writeln!(out, "    author: {}", signature)?;
+        writeln!(out, "    committer: {}", signature)?;
+        for u in &updates {
+            writeln!(out, "    file: {}", u.relative_path)?;
+        }
+    }
+    if opts.tag {
+        let target = match opts.bump_files {
+            true => String::from("the release commit"),
+            false => format!("HEAD ({})", &details.head.to_string()[..7]),
+        };
+        writeln!(out, "\nWould tag {} as {}", target, version)?;
+        writeln!(out, "    tagger: {}", signature)?;
+    }
+    Ok(vec![])
+}
@@ -0,0 +1,126 @@
+use std::io::IsTerminal;
+
+use clap::{CommandFactory, ErrorKind, Parser};
+use tracing::Level;
+
+mod cli;
+mod commands;
+mod dry_run;
+mod output;
+
+use cli::{CmdArgs, Commands, ConfigCommands};
+use commands::RunOptions;
+
+/// Logs to stderr, at warn level unless raised by -v or lowered by --quiet.
+fn init_logging(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => Level::ERROR,
+        (false, 0) => Level::WARN,
+        (false, 1) => Level::INFO,
+        (false, 2) => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_target(false)
+        .without_time()
+        .init();
+}
+
+/// Number of `-v` flags given in short form, including grouped flags such as `-vr`.
+fn short_v_count() -> u8 {
+    std::env::args().skip(1)
+        .take_while(|a| a != "--")
+        .filter(|a| a.starts_with('-') && !a.starts_with("--"))
+        .map(|a| a.chars().filter(|c| *c == 'v').count() as u8)
+        .sum()
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut args = CmdArgs::parse();
+    // Without a subcommand, -v keeps its old meaning of --lead-v.
+    let legacy_lead_v = match args.command {
+        None => short_v_count().min(args.verbose),
+        Some(_) => 0,
+    };
+    if legacy_lead_v > 0 {
+        args.verbose -= legacy_lead_v;
+        args.lead_v = true;
+    }
+    init_logging(args.verbose, args.quiet);
+    if legacy_lead_v > 0 {
+        tracing::warn!("-v without a subcommand is deprecated, use --lead-v, -v means verbose with subcommands");
+    }
+    // Only the global -v and --quiet flags may precede a subcommand.
+    if args.command.is_some() && args.has_legacy_args() {
+        CmdArgs::command()
+            .error(ErrorKind::ArgumentConflict, "the legacy flags and path cannot be used with a subcommand")
+            .exit();
+    }
+
+    match &args.command {
+        Some(Commands::Current(repo)) => commands::current(repo),
+        Some(Commands::Next{release, output, repo}) => commands::run(&repo.path, &RunOptions{
+            release: *release,
+            lead_v: repo.lead_v,
+            bump_files: false,
+            tag: false,
+            notes: false,
+            dry_run: false,
+            require_release: false,
+        }, output),
+        Some(Commands::Release{dry_run, output, repo}) => commands::run(&repo.path, &RunOptions{
+            release: true,
+            lead_v: repo.lead_v,
+            bump_files: true,
+            tag: true,
+            notes: false,
+            dry_run: *dry_run,
+            require_release: true,
+        }, output),
+        Some(Commands::Tag{prerelease, dry_run, output, repo}) => commands::run(&repo.path, &RunOptions{
+            release: !prerelease,
+            lead_v: repo.lead_v,
+            bump_files: false,
+            tag: true,
+            notes: false,
+            dry_run: *dry_run,
+            require_release: false,
+        }, output),
+        Some(Commands::Plan{out, repo}) => commands::plan(out.as_deref(), repo),
+        Some(Commands::Apply{plan, path}) => commands::apply(plan, path),
+        Some(Commands::Changelog{prerelease, repo}) => commands::changelog(*prerelease, repo),
+        Some(Commands::Lint(repo)) => commands::lint(repo),
+        Some(Commands::Check{fix, repo}) => commands::check(*fix, repo),
+        Some(Commands::Init{force, repo}) => commands::init(*force, repo),
+        Some(Commands::Config(ConfigCommands::Show{format})) => commands::config_show(*format),
+        Some(Commands::Config(ConfigCommands::Validate{path})) => commands::config_validate(path),
+        Some(Commands::Completions{shell}) => {
+            clap_complete::generate(*shell, &mut CmdArgs::command(), "conver", &mut std::io::stdout());
+            Ok(())
+        },
+        Some(Commands::Man) => {
+            clap_mangen::Man::new(CmdArgs::command()).render(&mut std::io::stdout())?;
+            Ok(())
+        },
+        None => {
+            if args.release || args.tag || args.bump_files || args.notes {
+                tracing::warn!("the -r, -t, -f and -n flags are deprecated, \
+                    use `conver next`, `conver tag`, `conver release` or `conver changelog`");
+            }
+            // -f has always implied tagging the release.
+            commands::run(&args.path, &RunOptions{
+                release: args.release,
+                lead_v: args.lead_v,
+                bump_files: args.bump_files,
+                tag: args.tag || args.bump_files,
+                notes: args.notes,
+                dry_run: args.dry_run,
+                // The deprecated -f keeps skipping the release on a dirty or tagged repository.
+                require_release: false,
+            }, &args.output)
+        },
+    }
+}
@@ -0,0 +1,64 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::cli::EmitFormat;
+
+/// Everything `conver` derived and did, printed by `--output json`.
+#[derive(Serialize, Debug)]
+pub struct JsonOutput {
+    pub previous_version: Option<String>,
+    pub version: String,
+    pub bump: String,
+    pub commit_count: u32,
+    pub head: String,
+    pub dirty: bool,
+    pub tagged: bool,
+    pub commit_created: bool,
+    pub commit_oid: Option<String>,
+    pub tag_created: bool,
+    pub tag_oid: Option<String>,
+    pub version_file_errors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// Writes `outputs` in the format expected by the CI system.
+pub fn emit_outputs(emit: EmitFormat, emit_file: Option<&str>, outputs: &[(&str, String)]) -> anyhow::Result<()> {
+    match emit {
+        EmitFormat::Github => {
+            let path = match emit_file {
+                Some(f) => f.to_string(),
+                None => std::env::var("GITHUB_OUTPUT")
+                    .map_err(|_| anyhow::anyhow!("GITHUB_OUTPUT is not set, use --emit-file outside of GitHub Actions"))?,
+            };
+            let lines: String = outputs.iter()
+                .map(|(key, value)| format!("{}={}\n", key, value))
+                .collect();
+            let mut file = OpenOptions::new().create(true).append(true).open(&path)
+                .with_context(|| format!("Failed to open GitHub output file {}", path))?;
+            file.write_all(lines.as_bytes())?;
+        },
+        EmitFormat::Gitlab | EmitFormat::Dotenv => {
+            let lines: Vec<(String, &String)> = outputs.iter()
+                .map(|(key, value)| (key.to_uppercase(), value))
+                .collect();
+            match (emit, emit_file) {
+                (EmitFormat::Dotenv, None) => lines.iter().for_each(|(key, value)| {
+                    println!("export {}='{}'", key, value.replace('\'', "'\\''"));
+                }),
+                (_, path) => {
+                    let path = path.unwrap_or("conver.env");
+                    let contents: String = lines.iter()
+                        .map(|(key, value)| format!("{}={}\n", key, value))
+                        .collect();
+                    std::fs::write(path, contents)
+                        .with_context(|| format!("Failed to write dotenv file {}", path))?;
+                },
+            }
+        },
+    }
+    Ok(())
+}
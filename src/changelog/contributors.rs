@@ -0,0 +1,74 @@
+use std::collections::HashSet;
+
+use git2::{Commit, Mailmap, Oid, Repository, Signature, Time};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static CO_AUTHOR_MATCHER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?im)^co-authored-by:\s*(.+?)\s*<([^>]+)>\s*$").expect("Invalid co-author matcher")
+});
+
+/// An author or co-author of the commits in a release.
+#[derive(Debug, Clone)]
+pub struct Contributor {
+    pub name: String,
+    pub email: String,
+    /// No commit before the previous release was attributed to this contributor.
+    pub first_time: bool,
+}
+
+/// Collects the authors and `Co-authored-by:` trailers of `commits`,
+/// de-duplicated through the repository's `.mailmap`.
+/// Contributors without any commit reachable from `base` are flagged as first-time.
+pub(crate) fn collect_contributors(
+    repo: &Repository,
+    commits: &[Oid],
+    base: Option<Oid>,
+) -> Result<Vec<Contributor>, git2::Error> {
+    let mailmap = repo.mailmap()?;
+    let mut seen = HashSet::new();
+    let mut contributors = vec![];
+    for oid in commits {
+        let commit = repo.find_commit(*oid)?;
+        for (name, email) in commit_identities(&commit, &mailmap)? {
+            if seen.insert(email.to_lowercase()) {
+                contributors.push(Contributor{name, email, first_time: true});
+            }
+        }
+    }
+
+    if let Some(base) = base {
+        let mut previous = HashSet::new();
+        let mut refs = repo.revwalk()?;
+        refs.push(base)?;
+        for oid in refs {
+            let commit = repo.find_commit(oid?)?;
+            for (_, email) in commit_identities(&commit, &mailmap)? {
+                previous.insert(email.to_lowercase());
+            }
+        }
+        contributors.iter_mut()
+            .for_each(|c| c.first_time = !previous.contains(&c.email.to_lowercase()));
+    }
+
+    contributors.sort_by_key(|c| c.name.to_lowercase());
+    Ok(contributors)
+}
+
+/// Returns the mailmapped name and email of the author and every co-author of `commit`.
+fn commit_identities(commit: &Commit, mailmap: &Mailmap) -> Result<Vec<(String, String)>, git2::Error> {
+    let mut identities = vec![signature_identity(&commit.author_with_mailmap(mailmap)?)];
+    let message = commit.message().unwrap_or_default();
+    for cap in CO_AUTHOR_MATCHER.captures_iter(message) {
+        let sig = Signature::new(&cap[1], &cap[2], &Time::new(0, 0))?;
+        identities.push(signature_identity(&mailmap.resolve_signature(&sig)?));
+    }
+    Ok(identities)
+}
+
+fn signature_identity(sig: &Signature) -> (String, String) {
+    (
+        sig.name().unwrap_or_default().to_string(),
+        sig.email().unwrap_or_default().to_string(),
+    )
+}
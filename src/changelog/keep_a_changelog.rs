@@ -0,0 +1,146 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::Error;
+
+static UNRELEASED_HEADING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?im)^##[ \t]*\[unreleased\].*$").expect("Invalid unreleased heading matcher")
+});
+static UNRELEASED_LINK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?im)^\[unreleased\]:[ \t]*(\S+)[ \t]*$").expect("Invalid unreleased link matcher")
+});
+
+/// Releases the `## [Unreleased]` section of a Keep a Changelog file as `version`.
+/// A fresh, empty Unreleased section is inserted above it and the `[Unreleased]`
+/// compare link at the bottom of the file is moved to start from `tag`.
+pub fn release_unreleased(contents: &str, version: &str, tag: &str, date: &str) -> Result<String, Error> {
+    let headings: Vec<_> = UNRELEASED_HEADING.find_iter(contents).collect();
+    let heading = match headings.as_slice() {
+        [] => return Err(Error::MissingUnreleased),
+        [heading] => heading,
+        _ => return Err(Error::MalformedChangelog{
+            reason: String::from("more than one `## [Unreleased]` section found")
+        }),
+    };
+    let existing = Regex::new(&format!(r"(?m)^##[ \t]*\[{}\]", regex::escape(version)))
+        .expect("Invalid version heading matcher");
+    if existing.is_match(contents) {
+        return Err(Error::MalformedChangelog{
+            reason: format!("a `## [{}]` section already exists", version)
+        });
+    }
+
+    let mut out = String::with_capacity(contents.len() + 128);
+    out.push_str(&contents[..heading.start()]);
+    out.push_str(&format!("## [Unreleased]\n\n## [{}] - {}", version, date));
+    let rest = &contents[heading.end()..];
+
+    match UNRELEASED_LINK.captures(rest) {
+        None => out.push_str(rest),
+        Some(cap) => {
+            let link = cap.get(0).expect("Missing link match");
+            let url = &cap[1];
+            let (unreleased, released) = move_compare_link(url, version, tag)
+                .ok_or_else(|| Error::MalformedChangelog{
+                    reason: format!("unable to update the `[Unreleased]` link {}", url)
+                })?;
+            out.push_str(&rest[..link.start()]);
+            out.push_str(&format!("[Unreleased]: {}\n{}", unreleased, released));
+            out.push_str(&rest[link.end()..]);
+        },
+    }
+    Ok(out)
+}
+
+/// Splits a `{base}/{previous}...HEAD` compare url into the new Unreleased url
+/// and the link reference for the released version.
+fn move_compare_link(url: &str, version: &str, tag: &str) -> Option<(String, String)> {
+    let url = url.strip_suffix("HEAD")?;
+    let (url, separator) = match url.strip_suffix("...") {
+        Some(u) => (u, "..."),
+        None => (url.strip_suffix("..")?, ".."),
+    };
+    let (base, previous) = url.rsplit_once('/')?;
+    if previous.is_empty() {
+        return None;
+    }
+    Some((
+        format!("{}/{}{}HEAD", base, tag, separator),
+        format!("[{}]: {}/{}{}{}", version, base, previous, separator, tag),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- Something new
+
+## [1.0.0] - 2024-01-01
+
+- First release
+
+[Unreleased]: https://github.com/o/r/compare/v1.0.0...HEAD
+[1.0.0]: https://github.com/o/r/releases/tag/v1.0.0
+";
+
+    #[test]
+    fn releases_the_unreleased_section() {
+        let released = release_unreleased(CHANGELOG, "1.1.0", "v1.1.0", "2024-02-01").unwrap();
+        assert_eq!(released, "# Changelog
+
+## [Unreleased]
+
+## [1.1.0] - 2024-02-01
+
+### Added
+
+- Something new
+
+## [1.0.0] - 2024-01-01
+
+- First release
+
+[Unreleased]: https://github.com/o/r/compare/v1.1.0...HEAD
+[1.1.0]: https://github.com/o/r/compare/v1.0.0...v1.1.0
+[1.0.0]: https://github.com/o/r/releases/tag/v1.0.0
+");
+    }
+
+    #[test]
+    fn releases_without_a_compare_link() {
+        let released = release_unreleased("## [unreleased]\n- fix\n", "0.1.0", "v0.1.0", "2024-02-01").unwrap();
+        assert_eq!(released, "## [Unreleased]\n\n## [0.1.0] - 2024-02-01\n- fix\n");
+    }
+
+    #[test]
+    fn refuses_missing_or_duplicated_sections() {
+        assert!(matches!(
+            release_unreleased("# Changelog\n", "1.1.0", "v1.1.0", "2024-02-01"),
+            Err(Error::MissingUnreleased)));
+        assert!(matches!(
+            release_unreleased("## [Unreleased]\n## [Unreleased]\n", "1.1.0", "v1.1.0", "2024-02-01"),
+            Err(Error::MalformedChangelog{..})));
+        assert!(matches!(
+            release_unreleased(CHANGELOG, "1.0.0", "v1.0.0", "2024-02-01"),
+            Err(Error::MalformedChangelog{..})));
+    }
+
+    #[test]
+    fn moves_two_and_three_dot_compare_links() {
+        assert_eq!(
+            move_compare_link("https://gitlab.com/o/r/-/compare/v1.0.0..HEAD", "1.1.0", "v1.1.0"),
+            Some((
+                String::from("https://gitlab.com/o/r/-/compare/v1.1.0..HEAD"),
+                String::from("[1.1.0]: https://gitlab.com/o/r/-/compare/v1.0.0..v1.1.0"),
+            )));
+        assert_eq!(move_compare_link("https://github.com/o/r/compare/v1.0.0...main", "1.1.0", "v1.1.0"), None);
+        assert_eq!(move_compare_link("https://github.com/o/r/compare/...HEAD", "1.1.0", "v1.1.0"), None);
+    }
+}
@@ -0,0 +1,227 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::config::LinksConfig;
+
+static ISSUE_MATCHER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(^|[^\w\[/&])#(\d+)\b").expect("Invalid issue matcher")
+});
+static TRACKER_MATCHER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[A-Z][A-Z0-9]+-\d+$").expect("Invalid tracker matcher")
+});
+
+/// Known git hosting services and the shape of their urls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    GitHub,
+    GitLab,
+    Gitea,
+    Bitbucket,
+}
+
+impl Provider {
+    /// Parses a provider name from the `[links]` configuration.
+    pub fn from_name(name: &str) -> Option<Provider> {
+        match name.to_ascii_lowercase().as_str() {
+            "github" => Some(Provider::GitHub),
+            "gitlab" => Some(Provider::GitLab),
+            "gitea" | "forgejo" | "codeberg" => Some(Provider::Gitea),
+            "bitbucket" => Some(Provider::Bitbucket),
+            _ => None,
+        }
+    }
+
+    /// Guesses the provider from the host name of a repository url.
+    pub fn from_url(url: &str) -> Option<Provider> {
+        let host = url.split("://").last()?.split('/').next()?.to_ascii_lowercase();
+        if host.contains("github") {
+            Some(Provider::GitHub)
+        } else if host.contains("gitlab") {
+            Some(Provider::GitLab)
+        } else if host.contains("bitbucket") {
+            Some(Provider::Bitbucket)
+        } else if host.contains("gitea") || host.contains("codeberg") || host.contains("forgejo") {
+            Some(Provider::Gitea)
+        } else {
+            None
+        }
+    }
+
+    fn commit_template(&self) -> &'static str {
+        match self {
+            Provider::GitHub | Provider::Gitea => "{host}/commit/{sha}",
+            Provider::GitLab => "{host}/-/commit/{sha}",
+            Provider::Bitbucket => "{host}/commits/{sha}",
+        }
+    }
+
+    fn compare_template(&self) -> &'static str {
+        match self {
+            Provider::GitHub | Provider::Gitea => "{host}/compare/{previous}...{current}",
+            Provider::GitLab => "{host}/-/compare/{previous}...{current}",
+            Provider::Bitbucket => "{host}/branches/compare/{current}%0D{previous}",
+        }
+    }
+
+    fn issue_template(&self) -> &'static str {
+        match self {
+            Provider::GitHub | Provider::Gitea | Provider::Bitbucket => "{host}/issues/{id}",
+            Provider::GitLab => "{host}/-/issues/{id}",
+        }
+    }
+}
+
+/// Resolved link templates used when rendering release notes.
+#[derive(Debug, Clone)]
+pub struct Links {
+    commit: Option<String>,
+    compare: Option<String>,
+    issue: Option<String>,
+    tracker: Option<String>,
+}
+
+impl Links {
+    /// Builds link templates from the `[links]` configuration,
+    /// falling back to the shape of the `origin` remote url.
+    /// Returns None when neither describes a known host.
+    pub fn resolve(config: Option<&LinksConfig>, remote_url: Option<&str>) -> Option<Links> {
+        let default_config = LinksConfig::default();
+        let config = config.unwrap_or(&default_config);
+        let host = config.host.as_ref()
+            .map(|h| h.trim_end_matches('/').to_string())
+            .or_else(|| repository_url(remote_url?));
+        let provider = config.provider.as_deref()
+            .and_then(Provider::from_name)
+            .or_else(|| Provider::from_url(host.as_ref()?));
+
+        let template = |configured: &Option<String>, derived: fn(&Provider) -> &'static str| {
+            let template = configured.clone()
+                .or_else(|| provider.as_ref().map(|p| derived(p).to_string()))?;
+            match &host {
+                Some(host) => Some(template.replace("{host}", host)),
+                None if !template.contains("{host}") => Some(template),
+                None => None,
+            }
+        };
+        let links = Links {
+            commit: template(&config.commit, Provider::commit_template),
+            compare: template(&config.compare, Provider::compare_template),
+            issue: template(&config.issue, Provider::issue_template),
+            tracker: config.tracker.clone(),
+        };
+        if links.commit.is_none() && links.compare.is_none()
+            && links.issue.is_none() && links.tracker.is_none() {
+            return None;
+        }
+        Some(links)
+    }
+
+    /// Renders a short commit sha, linked when a commit template is known.
+    pub fn commit(&self, sha: &str) -> String {
+        let short = &sha[..sha.len().min(7)];
+        match &self.commit {
+            Some(t) => format!("[{}]({})", short, t.replace("{sha}", sha)),
+            None => short.to_string(),
+        }
+    }
+
+    /// Returns the url comparing two revisions, if a compare template is known.
+    pub fn compare(&self, previous: &str, current: &str) -> Option<String> {
+        Some(self.compare.as_ref()?
+            .replace("{previous}", previous)
+            .replace("{current}", current))
+    }
+
+    /// Renders a single issue reference such as `#123` or `ABC-42`.
+    pub fn reference(&self, reference: &str) -> String {
+        let reference = reference.trim();
+        if let Some(id) = reference.strip_prefix('#') {
+            if let Some(t) = &self.issue {
+                return format!("[#{}]({})", id, t.replace("{id}", id));
+            }
+        } else if TRACKER_MATCHER.is_match(reference) {
+            if let Some(t) = &self.tracker {
+                return format!("[{}]({})", reference, t.replace("{id}", reference));
+            }
+        }
+        reference.to_string()
+    }
+
+    /// Links every `#123` reference found in free text.
+    pub fn issues_in(&self, text: &str) -> String {
+        if self.issue.is_none() {
+            return text.to_string();
+        }
+        ISSUE_MATCHER.replace_all(text, |caps: &Captures| {
+            format!("{}{}", &caps[1], self.reference(&format!("#{}", &caps[2])))
+        }).into_owned()
+    }
+}
+
+/// Converts a git remote url into the https url of the repository.
+/// Supports `https://`, `ssh://` and scp-like `git@host:owner/repo.git` remotes.
+pub fn repository_url(remote: &str) -> Option<String> {
+    let remote = remote.trim().trim_end_matches('/');
+    let remote = remote.strip_suffix(".git").unwrap_or(remote);
+    let (scheme, host, path) = match remote.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            match scheme {
+                "http" | "https" => (scheme, host, path),
+                // Ssh ports never match the web interface.
+                _ => ("https", host.split(':').next()?, path),
+            }
+        },
+        None => {
+            let (authority, path) = remote.split_once(':')?;
+            ("https", authority.rsplit('@').next()?, path)
+        },
+    };
+    let path = path.trim_start_matches('/');
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some(format!("{}://{}/{}", scheme, host, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_remotes_to_repository_urls() {
+        for remote in [
+            "https://github.com/o/r.git",
+            "https://token@github.com/o/r/",
+            "ssh://git@github.com/o/r.git",
+            "ssh://git@github.com:2222/o/r",
+            "git@github.com:o/r.git",
+            "github.com:/o/r",
+        ] {
+            assert_eq!(repository_url(remote).as_deref(), Some("https://github.com/o/r"), "{}", remote);
+        }
+        assert_eq!(repository_url("http://gitea.local/o/r").as_deref(), Some("http://gitea.local/o/r"));
+        assert_eq!(repository_url("git@gitlab.com:group/sub/r.git").as_deref(), Some("https://gitlab.com/group/sub/r"));
+        assert_eq!(repository_url("/srv/git/r.git"), None);
+        assert_eq!(repository_url("https://github.com"), None);
+    }
+
+    #[test]
+    fn guesses_providers_from_urls() {
+        assert_eq!(Provider::from_url("https://github.com/o/r"), Some(Provider::GitHub));
+        assert_eq!(Provider::from_url("https://gitlab.example.com/o/r"), Some(Provider::GitLab));
+        assert_eq!(Provider::from_url("https://codeberg.org/o/r"), Some(Provider::Gitea));
+        assert_eq!(Provider::from_url("https://bitbucket.org/o/r"), Some(Provider::Bitbucket));
+        assert_eq!(Provider::from_url("https://example.com/o/r"), None);
+    }
+
+    #[test]
+    fn resolves_links_from_the_remote() {
+        let links = Links::resolve(None, Some("git@gitlab.com:o/r.git")).unwrap();
+        assert_eq!(links.commit("0123456789"), "[0123456](https://gitlab.com/o/r/-/commit/0123456789)");
+        assert_eq!(links.compare("v1.0.0", "v1.1.0").as_deref(), Some("https://gitlab.com/o/r/-/compare/v1.0.0...v1.1.0"));
+        assert_eq!(links.issues_in("fixes #12"), "fixes [#12](https://gitlab.com/o/r/-/issues/12)");
+        assert!(Links::resolve(None, Some("git@example.com:o/r.git")).is_none());
+    }
+}
@@ -0,0 +1,170 @@
+extern crate custom_error;
+use custom_error::custom_error;
+use std::str::FromStr;
+use git2::{Oid, Repository};
+
+mod links;
+mod contributors;
+mod keep_a_changelog;
+mod packaging;
+pub use links::{Links, Provider, repository_url};
+pub use contributors::Contributor;
+pub(crate) use contributors::collect_contributors;
+pub use keep_a_changelog::release_unreleased;
+pub use packaging::{
+    PackageOptions, package_version, render_debian_entry, render_rpm_entry, prepend_debian, insert_rpm
+};
+
+custom_error! { pub Error
+    MissingUnreleased = "no `## [Unreleased]` section found",
+    MalformedChangelog{reason: String} = "malformed changelog, {reason}",
+}
+
+/// Supported formats of `changelog_files`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangelogFormat {
+    KeepAChangelog,
+    Debian,
+    Rpm,
+}
+
+impl FromStr for ChangelogFormat {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<ChangelogFormat, crate::Error> {
+        match s {
+            "keep-a-changelog" => Ok(ChangelogFormat::KeepAChangelog),
+            "debian" => Ok(ChangelogFormat::Debian),
+            "rpm" => Ok(ChangelogFormat::Rpm),
+            _ => Err(crate::Error::InvalidConfigError{
+                reason: format!("unsupported changelog format {}", s)
+            }),
+        }
+    }
+}
+
+/// Footer tokens whose values reference issues.
+const REFERENCE_TOKENS: [&str; 7] = ["closes", "close", "fixes", "fix", "resolves", "refs", "ref"];
+
+/// A conventional commit included in a release.
+#[derive(Debug, Clone)]
+pub struct ChangelogCommit {
+    pub id: Oid,
+    pub type_: String,
+    pub scope: Option<String>,
+    pub description: String,
+    pub breaking: bool,
+    pub breaking_description: Option<String>,
+    pub references: Vec<String>,
+}
+
+impl ChangelogCommit {
+    /// Parses the commit message of `oid`.
+    /// Returns None for commits that do not follow conventional commits.
+    pub fn from_oid(repo: &Repository, oid: Oid) -> Option<ChangelogCommit> {
+        let commit = repo.find_commit(oid).ok()?;
+        let parsed = git_conventional::Commit::parse(commit.message()?).ok()?;
+        let references = parsed.footers().iter()
+            .filter(|f| REFERENCE_TOKENS.contains(&f.token().as_str().to_ascii_lowercase().as_str()))
+            .flat_map(|f| {
+                let is_ref = f.separator() == git_conventional::FooterSeparator::Ref;
+                f.value().split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(move |v| match is_ref {
+                        true => format!("#{}", v),
+                        false => v.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+            }).collect();
+        Some(ChangelogCommit {
+            id: oid,
+            type_: parsed.type_().as_str().to_ascii_lowercase(),
+            scope: parsed.scope().map(|s| s.as_str().to_string()),
+            description: parsed.description().to_string(),
+            breaking: parsed.breaking(),
+            breaking_description: parsed.breaking_description()
+                .filter(|d| *d != parsed.description())
+                .map(String::from),
+            references,
+        })
+    }
+}
+
+/// Commits between the previous release and the current one.
+#[derive(Debug)]
+pub struct ReleaseNotes {
+    pub version: String,
+    pub previous_version: Option<String>,
+    pub commits: Vec<ChangelogCommit>,
+    pub contributors: Vec<Contributor>,
+    pub links: Option<Links>,
+}
+
+impl ReleaseNotes {
+    /// Renders the release notes as a markdown section.
+    pub fn render_markdown(&self) -> String {
+        let mut out = String::new();
+        let compare = self.links.as_ref().zip(self.previous_version.as_ref())
+            .and_then(|(l, prev)| l.compare(prev, &self.version));
+        match compare {
+            Some(url) => out.push_str(&format!("## [{}]({})\n", self.version, url)),
+            None => out.push_str(&format!("## {}\n", self.version)),
+        }
+
+        let breaking: Vec<&ChangelogCommit> = self.commits.iter().filter(|c| c.breaking).collect();
+        if !breaking.is_empty() {
+            out.push_str("\n### ⚠ BREAKING CHANGES\n\n");
+            for c in breaking {
+                let description = c.breaking_description.as_ref().unwrap_or(&c.description);
+                out.push_str(&self.render_entry(c, description));
+            }
+        }
+        for (type_, title) in [
+            ("feat", "Features"),
+            ("fix", "Bug Fixes"),
+            ("perf", "Performance Improvements"),
+            ("revert", "Reverts"),
+        ] {
+            let section: Vec<&ChangelogCommit> = self.commits.iter()
+                .filter(|c| c.type_ == type_).collect();
+            if section.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("\n### {}\n\n", title));
+            for c in section {
+                out.push_str(&self.render_entry(c, &c.description));
+            }
+        }
+        if !self.contributors.is_empty() {
+            out.push_str("\n### Contributors\n\n");
+            for c in &self.contributors {
+                match c.first_time {
+                    true => out.push_str(&format!("* {} (first contribution)\n", c.name)),
+                    false => out.push_str(&format!("* {}\n", c.name)),
+                }
+            }
+        }
+        out
+    }
+
+    fn render_entry(&self, commit: &ChangelogCommit, description: &str) -> String {
+        let sha = commit.id.to_string();
+        let (description, sha, references) = match &self.links {
+            Some(l) => (
+                l.issues_in(description),
+                l.commit(&sha),
+                commit.references.iter().map(|r| l.reference(r)).collect::<Vec<String>>(),
+            ),
+            None => (description.to_string(), sha[..7].to_string(), commit.references.clone()),
+        };
+        let mut entry = match &commit.scope {
+            Some(scope) => format!("* **{}:** {} ({})", scope, description, sha),
+            None => format!("* {} ({})", description, sha),
+        };
+        if !references.is_empty() {
+            entry.push_str(&format!(", refs {}", references.join(", ")));
+        }
+        entry.push('\n');
+        entry
+    }
+}
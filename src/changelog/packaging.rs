@@ -0,0 +1,219 @@
+use chrono::{DateTime, Local};
+
+use super::{ChangelogCommit, Error, ReleaseNotes};
+
+/// Package metadata written into Debian and RPM changelog entries.
+#[derive(Debug, Clone)]
+pub struct PackageOptions {
+    pub package: Option<String>,
+    pub distribution: String,
+    pub urgency: String,
+    pub maintainer: String,
+    pub revision: String,
+}
+
+/// Converts a semantic version into a Debian/RPM upstream version.
+/// Prereleases use `~` so they sort before the final release,
+/// i.e. `1.2.0-3+abc1234` becomes `1.2.0~3+abc1234`.
+pub fn package_version(version: &str) -> String {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    version.replacen('-', "~", 1)
+}
+
+/// Lines describing the notable commits of a release.
+fn change_lines(notes: &ReleaseNotes) -> Vec<String> {
+    let describe = |c: &ChangelogCommit, description: &str| match &c.scope {
+        Some(scope) => format!("{}: {}", scope, description),
+        None => description.to_string(),
+    };
+    let mut lines: Vec<String> = notes.commits.iter()
+        .filter(|c| c.breaking)
+        .map(|c| format!("BREAKING: {}", describe(c, c.breaking_description.as_ref().unwrap_or(&c.description))))
+        .collect();
+    for type_ in ["feat", "fix", "perf", "revert"] {
+        lines.extend(notes.commits.iter()
+            .filter(|c| c.type_ == type_ && !c.breaking)
+            .map(|c| describe(c, &c.description)));
+    }
+    if lines.is_empty() {
+        lines.push(format!("Release {}", package_version(&notes.version)));
+    }
+    lines
+}
+
+/// Renders a `debian/changelog` entry for the release.
+pub fn render_debian_entry(notes: &ReleaseNotes, options: &PackageOptions, date: &DateTime<Local>) -> Result<String, Error> {
+    let package = options.package.as_ref().ok_or_else(|| Error::MalformedChangelog{
+        reason: String::from("debian changelogs require a package name")
+    })?;
+    let mut entry = format!("{} ({}-{}) {}; urgency={}\n\n",
+        package,
+        package_version(&notes.version),
+        options.revision,
+        options.distribution,
+        options.urgency);
+    for line in change_lines(notes) {
+        entry.push_str(&format!("  * {}\n", line));
+    }
+    entry.push_str(&format!("\n -- {}  {}\n", options.maintainer, date.to_rfc2822()));
+    Ok(entry)
+}
+
+/// Renders an entry of the `%changelog` section of an RPM spec file.
+pub fn render_rpm_entry(notes: &ReleaseNotes, options: &PackageOptions, date: &DateTime<Local>) -> String {
+    let mut entry = format!("* {} {} - {}-{}\n",
+        date.format("%a %b %d %Y"),
+        options.maintainer,
+        package_version(&notes.version),
+        options.revision);
+    for line in change_lines(notes) {
+        entry.push_str(&format!("- {}\n", line));
+    }
+    entry
+}
+
+/// Prepends `entry` to a Debian changelog, refusing to add a version twice.
+pub fn prepend_debian(contents: &str, entry: &str, options: &PackageOptions, version: &str) -> Result<String, Error> {
+    let heading = format!("{} ({}-{})",
+        options.package.as_deref().unwrap_or_default(),
+        package_version(version),
+        options.revision);
+    if contents.lines().any(|l| l.starts_with(&heading)) {
+        return Err(Error::MalformedChangelog{
+            reason: format!("an entry for {} already exists", heading)
+        });
+    }
+    match contents.is_empty() {
+        true => Ok(entry.to_string()),
+        false => Ok(format!("{}\n{}", entry, contents)),
+    }
+}
+
+/// Inserts `entry` at the top of the `%changelog` section of a spec file,
+/// appending the section when the spec file does not have one.
+pub fn insert_rpm(contents: &str, entry: &str) -> String {
+    let mut offset = 0;
+    for line in contents.split_inclusive('\n') {
+        offset += line.len();
+        if line.trim_end() == "%changelog" {
+            let rest = &contents[offset..];
+            let separator = match rest.trim().is_empty() {
+                true => "",
+                false => "\n",
+            };
+            let newline = match line.ends_with('\n') {
+                true => "",
+                false => "\n",
+            };
+            return format!("{}{}{}{}{}", &contents[..offset], newline, entry, separator, rest);
+        }
+    }
+    let separator = match contents.is_empty() || contents.ends_with("\n\n") {
+        true => "",
+        false if contents.ends_with('\n') => "\n",
+        false => "\n\n",
+    };
+    format!("{}{}%changelog\n{}", contents, separator, entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use git2::Oid;
+
+    use super::*;
+
+    fn commit(type_: &str, scope: Option<&str>, description: &str, breaking: bool) -> ChangelogCommit {
+        ChangelogCommit {
+            id: Oid::zero(),
+            type_: type_.to_string(),
+            scope: scope.map(String::from),
+            description: description.to_string(),
+            breaking,
+            breaking_description: None,
+            references: vec![],
+        }
+    }
+
+    fn notes(commits: Vec<ChangelogCommit>) -> ReleaseNotes {
+        ReleaseNotes {
+            version: String::from("v1.2.0-3+abc1234"),
+            previous_version: None,
+            commits,
+            contributors: vec![],
+            links: None,
+        }
+    }
+
+    fn options() -> PackageOptions {
+        PackageOptions {
+            package: Some(String::from("conver")),
+            distribution: String::from("unstable"),
+            urgency: String::from("medium"),
+            maintainer: String::from("Jane Doe <jane@example.com>"),
+            revision: String::from("1"),
+        }
+    }
+
+    fn date() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 2, 1, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn package_versions_sort_prereleases_first() {
+        assert_eq!(package_version("v1.2.0"), "1.2.0");
+        assert_eq!(package_version("1.2.0-3+abc1234"), "1.2.0~3+abc1234");
+        assert_eq!(package_version("1.2.0-rc-1"), "1.2.0~rc-1");
+    }
+
+    #[test]
+    fn renders_debian_entries() {
+        let notes = notes(vec![
+            commit("fix", None, "fix a crash", false),
+            commit("feat", Some("cli"), "add a flag", false),
+            commit("feat", None, "drop the old flag", true),
+            commit("chore", None, "tidy up", false),
+        ]);
+        let entry = render_debian_entry(&notes, &options(), &date()).unwrap();
+        assert_eq!(entry, format!("conver (1.2.0~3+abc1234-1) unstable; urgency=medium
+
+  * BREAKING: drop the old flag
+  * cli: add a flag
+  * fix a crash
+
+ -- Jane Doe <jane@example.com>  {}
+", date().to_rfc2822()));
+
+        let unnamed = PackageOptions{package: None, ..options()};
+        assert!(render_debian_entry(&notes, &unnamed, &date()).is_err());
+    }
+
+    #[test]
+    fn renders_rpm_entries() {
+        let entry = render_rpm_entry(&notes(vec![]), &options(), &date());
+        assert_eq!(entry, format!("* {} Jane Doe <jane@example.com> - 1.2.0~3+abc1234-1\n- Release 1.2.0~3+abc1234\n",
+            date().format("%a %b %d %Y")));
+    }
+
+    #[test]
+    fn prepends_debian_entries_once() {
+        let entry = "conver (1.2.0-1) unstable; urgency=medium\n";
+        assert_eq!(prepend_debian("", entry, &options(), "1.2.0").unwrap(), entry);
+        let contents = "conver (1.1.0-1) unstable; urgency=medium\n";
+        assert_eq!(
+            prepend_debian(contents, entry, &options(), "1.2.0").unwrap(),
+            format!("{}\n{}", entry, contents));
+        assert!(prepend_debian(entry, entry, &options(), "v1.2.0").is_err());
+    }
+
+    #[test]
+    fn inserts_rpm_entries() {
+        let entry = "* Thu Feb 01 2024 Jane - 1.2.0-1\n- fix\n";
+        assert_eq!(
+            insert_rpm("Name: conver\n\n%changelog\n* Mon Jan 01 2024 Jane - 1.1.0-1\n- feat\n", entry),
+            "Name: conver\n\n%changelog\n* Thu Feb 01 2024 Jane - 1.2.0-1\n- fix\n\n* Mon Jan 01 2024 Jane - 1.1.0-1\n- feat\n");
+        assert_eq!(insert_rpm("Name: conver\n%changelog", entry), format!("Name: conver\n%changelog\n{}", entry));
+        assert_eq!(insert_rpm("Name: conver\n", entry), format!("Name: conver\n\n%changelog\n{}", entry));
+        assert_eq!(insert_rpm("", entry), format!("%changelog\n{}", entry));
+    }
+}
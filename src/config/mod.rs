@@ -1,20 +1,37 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::Path;
 use std::{fs, io};
-use std::str::FromStr;
 
-mod presets;
-use presets::FilePresets;
+pub mod presets;
+pub mod validate;
 
-const CONFIG_PATH: &str = "conventional_release.toml";
+pub const CONFIG_PATH: &str = "conventional_release.toml";
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct ConventionalSemverConfig {
     #[serde(default = "ConventionalSemverConfig::default_v")]
     pub v: bool,
+    /// Version bumped from while the repository has no release tag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version_files: Option<Vec<VersionFileConfig>>,
     #[serde(default = "CommitSignature::default_sig")]
     pub commit_signature: CommitSignature,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<LinksConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changelog_files: Option<Vec<ChangelogFileConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_files: Option<Vec<GeneratedFileConfig>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspaces: Option<Vec<WorkspaceConfig>>,
+    /// Named presets for the repository's own version files, `[presets.<name>]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presets: Option<BTreeMap<String, VersionFileConfig>>,
 }
 
 impl ConventionalSemverConfig {
@@ -26,7 +43,24 @@ impl ConventionalSemverConfig {
     }
 }
 
-#[derive(Deserialize, Debug)]
+impl Default for ConventionalSemverConfig {
+    fn default() -> Self {
+        Self {
+            v: false,
+            initial_version: None,
+            commit_signature: CommitSignature::default_sig(),
+            version_files: None,
+            links: None,
+            changelog_files: None,
+            generated_files: None,
+            workspaces: None,
+            presets: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct CommitSignature {
     #[serde(default = "CommitSignature::default_sig_name")]
     pub name: String,
@@ -51,31 +85,195 @@ impl CommitSignature {
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// Overrides for the links rendered in release notes.
+/// Any template left unset is derived from the `origin` remote.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LinksConfig {
+    /// Base url of the repository, i.e. `https://github.com/owner/repo`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// One of `github`, `gitlab`, `gitea` or `bitbucket`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Commit link template, supports `{host}` and `{sha}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Compare link template, supports `{host}`, `{previous}` and `{current}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compare: Option<String>,
+    /// Issue link template for `#123` references, supports `{host}` and `{id}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue: Option<String>,
+    /// Issue tracker link template for `ABC-42` references, supports `{id}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracker: Option<String>,
+}
+
+/// Describes a changelog file updated when bumping version files.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ChangelogFileConfig {
+    pub path: String,
+    /// One of `keep-a-changelog`, `debian` or `rpm`
+    pub format: String,
+    /// Source package name, required by `debian`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    #[serde(default = "ChangelogFileConfig::default_distribution")]
+    pub distribution: String,
+    #[serde(default = "ChangelogFileConfig::default_urgency")]
+    pub urgency: String,
+    /// Defaults to the `commit_signature`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintainer: Option<String>,
+    /// Debian revision or RPM release appended to the version
+    #[serde(default = "ChangelogFileConfig::default_revision")]
+    pub revision: String,
+}
+
+impl ChangelogFileConfig {
+    fn default_distribution() -> String {
+        String::from("unstable")
+    }
+
+    fn default_urgency() -> String {
+        String::from("medium")
+    }
+
+    fn default_revision() -> String {
+        String::from("1")
+    }
+}
+
+/// Describes a file rendered from a template when bumping version files.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GeneratedFileConfig {
+    /// Output path, relative to the repository
+    pub path: String,
+    /// Template file, relative to the repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Template written in the configuration, instead of a template file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inline: Option<String>,
+}
+
+/// Describes a workspace whose member manifests are discovered and bumped together.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// One of `cargo` or `npm`
+    pub ecosystem: String,
+    /// Directory of the workspace root, relative to the repository
+    #[serde(default = "WorkspaceConfig::default_path")]
+    pub path: String,
+}
+
+impl WorkspaceConfig {
+    fn default_path() -> String {
+        String::from(".")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct VersionFileConfig {
-    #[serde(default = "ConventionalSemverConfig::default_v")]
-    pub v: bool,
-    #[serde(default = "ConventionalSemverConfig::default_path")]
+    /// Unset fields are inherited from the preset, an explicit `false` overrides a preset's `true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub v: Option<bool>,
+    /// Relative path, or a glob such as `crates/*/Cargo.toml` matching several files
+    #[serde(default = "ConventionalSemverConfig::default_path", skip_serializing_if = "String::is_empty")]
     pub path: String,
+    /// A glob `path` matching no file is not an error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_no_matches: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version_postfix: Option<String>,
+    /// Match `version_prefix` and `version_postfix` as plain text instead of regexes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub literal: Option<bool>,
+    /// Matches of the prefix and postfix to replace, `first`, `all` or a number, defaults to `all`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occurrences: Option<OccurrencesConfig>,
+    /// One of `semver`, `pep440`, `assembly` or `docker-tag`, defaults to `semver`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_scheme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub preset: Option<String>,
 }
 
+/// `occurrences = "first"`, `"all"` or a number.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum OccurrencesConfig {
+    Count(usize),
+    Name(String),
+}
+
+impl fmt::Display for OccurrencesConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OccurrencesConfig::Count(n) => write!(f, "{}", n),
+            OccurrencesConfig::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl VersionFileConfig {
+    /// Fills the fields left unset with the values of `preset`.
+    /// A version file with its own `key` does not inherit the preset's regex, nor one with its own regex the preset's `key`.
+    pub fn apply_preset(&mut self, preset: &VersionFileConfig) {
+        self.v = self.v.or(preset.v);
+        self.allow_no_matches = self.allow_no_matches.or(preset.allow_no_matches);
+        if self.path.is_empty() {
+            self.path = preset.path.clone();
+        }
+        if self.version_prefix.is_none() && self.version_postfix.is_none() {
+            self.key = self.key.take().or_else(|| preset.key.clone());
+        }
+        if self.key.is_none() {
+            self.version_prefix = self.version_prefix.take().or_else(|| preset.version_prefix.clone());
+            self.version_postfix = self.version_postfix.take().or_else(|| preset.version_postfix.clone());
+            self.literal = self.literal.or(preset.literal);
+            self.occurrences = self.occurrences.take().or_else(|| preset.occurrences.clone());
+        }
+        self.version_scheme = self.version_scheme.take().or_else(|| preset.version_scheme.clone());
+    }
+
+    /// Whether the version is located by a key, prefix or postfix.
+    pub fn has_locator(&self) -> bool {
+        self.key.is_some() || self.version_prefix.is_some() || self.version_postfix.is_some()
+    }
+
+    /// The prefix and postfix regexes, escaped when matched literally.
+    pub fn matcher_parts(&self) -> (String, String) {
+        let part = |p: &Option<String>| match (p, self.literal.unwrap_or(false)) {
+            (Some(p), true) => regex::escape(p),
+            (Some(p), false) => p.clone(),
+            (None, _) => String::new(),
+        };
+        (part(&self.version_prefix), part(&self.version_postfix))
+    }
+}
+
 impl ConventionalSemverConfig {
     pub fn new(v: bool, commit_signature: CommitSignature, version_files: Vec<VersionFileConfig>) -> Self {
         Self {
             v,
+            initial_version: None,
             commit_signature,
-            version_files: Some(version_files)
-        }
-    }
-
-    pub fn default() -> Self {
-        Self {
-            v: false,
-            commit_signature: CommitSignature::default_sig(),
-            version_files: None
+            version_files: Some(version_files),
+            links: None,
+            changelog_files: None,
+            generated_files: None,
+            workspaces: None,
+            presets: None,
         }
     }
 
@@ -84,19 +282,30 @@ impl ConventionalSemverConfig {
         match fs::read_to_string(pth) {
             Ok(c_file) => {
                 let str = c_file.as_str();
-                let mut config = toml::from_str::<ConventionalSemverConfig>(str)?;
-                if config.version_files.is_some() {
-                    for mut f in config.version_files.as_mut().unwrap().iter_mut() {
-                        if let Some(pre) = &f.preset{
-                            let preset = FilePresets::from_str(&pre)?;
-                            let cp = presets::PRESETS.get(&preset).expect("Preset not part of preset map");
-                            f.v = cp.v.clone();
-                            f.path = cp.path.clone();
-                            f.version_prefix = cp.version_prefix.clone();
-                            f.version_postfix = cp.version_postfix.clone();
-                            f.preset = cp.preset.clone();
-                            ()
-                        } else if f.path == "" {
+                // Unknown keys are only reported, `config validate` is the strict check.
+                let (parsed, unknown) = validate::parse_ignoring_unknown_keys(str);
+                for d in &unknown {
+                    tracing::warn!("{}:{}, it is ignored", CONFIG_PATH, d);
+                }
+                let mut config = parsed?;
+                if let Some(version_files) = config.version_files.as_mut() {
+                    for f in version_files.iter_mut() {
+                        if let Some(pre) = f.preset.clone() {
+                            let preset = presets::find_preset(&pre, config.presets.as_ref())?;
+                            f.apply_preset(preset);
+                            // Presets of files that can live anywhere, such as __init__.py, have no path.
+                            if f.path.is_empty() {
+                                return Err(crate::Error::InvalidConfigError{
+                                    reason: format!("version_file path is required by preset {}", pre)
+                                })
+                            }
+                            // Presets such as kustomization.yaml can't know which value holds the version.
+                            if !f.has_locator() {
+                                return Err(crate::Error::InvalidConfigError{
+                                    reason: format!("version_file key is required by preset {}", pre)
+                                })
+                            }
+                        } else if f.path.is_empty() {
                             return Err(crate::Error::InvalidConfigError{
                                 reason: String::from("version_file path cannot be blank, without a preset")
                             })
@@ -107,7 +316,7 @@ impl ConventionalSemverConfig {
             },
             Err(err) => {
                 if err.kind() == io::ErrorKind::NotFound {
-                    eprintln!("convention_release.toml not found, using default configuration");
+                    tracing::info!("{} not found, using default configuration", CONFIG_PATH);
                     Ok(Self::default())
                 } else {
                     Err(err.into())
@@ -116,3 +325,20 @@ impl ConventionalSemverConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version_file(source: &str) -> VersionFileConfig {
+        toml::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn literal_prefix_and_postfix_are_escaped() {
+        let file = version_file("path = \"a\"\nversion_prefix = \"version (\"\nversion_postfix = \")\"\nliteral = true\n");
+        assert_eq!(file.matcher_parts(), (String::from("version \\("), String::from("\\)")));
+        let file = version_file("path = \"a\"\nversion_prefix = \"v(\"\n");
+        assert_eq!(file.matcher_parts(), (String::from("v("), String::new()));
+    }
+}
@@ -1,12 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::str::FromStr;
 use once_cell::sync::Lazy;
 use super::VersionFileConfig;
+use crate::release::VersionFile;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Hash, Eq, PartialEq)]
 pub enum FilePresets {
     CARGOTOML,
-    PACKAGEJSON
+    PACKAGEJSON,
+    PYPROJECTTOML,
+    POETRY,
+    SETUPCFG,
+    SETUPPY,
+    INITPY,
+    VERSIONPY,
+    POMXML,
+    GRADLEPROPERTIES,
+    BUILDGRADLE,
+    BUILDGRADLEKTS,
+    CSPROJ,
+    CSPROJASSEMBLY,
+    CHARTYAML,
+    CHARTAPPVERSION,
+    KUSTOMIZATION,
+    DOCKERFILE,
+    DOCKERFILEARG,
 }
 
 impl FromStr for FilePresets {
@@ -15,6 +35,23 @@ impl FromStr for FilePresets {
         match s {
             "Cargo.toml" => Ok(FilePresets::CARGOTOML),
             "package.json" => Ok(FilePresets::PACKAGEJSON),
+            "pyproject.toml" => Ok(FilePresets::PYPROJECTTOML),
+            "poetry" => Ok(FilePresets::POETRY),
+            "setup.cfg" => Ok(FilePresets::SETUPCFG),
+            "setup.py" => Ok(FilePresets::SETUPPY),
+            "__init__.py" => Ok(FilePresets::INITPY),
+            "_version.py" => Ok(FilePresets::VERSIONPY),
+            "pom.xml" => Ok(FilePresets::POMXML),
+            "gradle.properties" => Ok(FilePresets::GRADLEPROPERTIES),
+            "build.gradle" => Ok(FilePresets::BUILDGRADLE),
+            "build.gradle.kts" => Ok(FilePresets::BUILDGRADLEKTS),
+            "csproj" => Ok(FilePresets::CSPROJ),
+            "csproj-assembly" => Ok(FilePresets::CSPROJASSEMBLY),
+            "Chart.yaml" => Ok(FilePresets::CHARTYAML),
+            "Chart.yaml-app-version" => Ok(FilePresets::CHARTAPPVERSION),
+            "kustomization.yaml" => Ok(FilePresets::KUSTOMIZATION),
+            "Dockerfile" => Ok(FilePresets::DOCKERFILE),
+            "Dockerfile-arg" => Ok(FilePresets::DOCKERFILEARG),
             _ => {
                 Err(crate::Error::PresetError{bad_preset: String::from(s)})
             },
@@ -22,21 +59,111 @@ impl FromStr for FilePresets {
     }
 }
 
+fn preset(
+    name: &str,
+    path: &str,
+    key: Option<&str>,
+    prefix: Option<&str>,
+    postfix: Option<&str>,
+    scheme: Option<&str>,
+) -> VersionFileConfig {
+    VersionFileConfig{
+        v: None,
+        path: String::from(path),
+        allow_no_matches: None,
+        key: key.map(String::from),
+        version_prefix: prefix.map(String::from),
+        version_postfix: postfix.map(String::from),
+        literal: None,
+        occurrences: None,
+        version_scheme: scheme.map(String::from),
+        preset: Some(String::from(name)),
+    }
+}
+
 pub static PRESETS: Lazy<HashMap<FilePresets, VersionFileConfig>> = Lazy::new(|| {
     let mut m = HashMap::new();
-    m.insert(FilePresets::CARGOTOML, VersionFileConfig{
-        v: false,
-        path: String::from("Cargo.toml"),
-        version_prefix: Some(String::from("version = \"")),
-        version_postfix: Some(String::from("\"[^,]")),
-        preset: Some(String::from("Cargo.toml")),
-    });
-    m.insert(FilePresets::PACKAGEJSON, VersionFileConfig{
-        v: false,
-        path: String::from("package.json"),
-        version_prefix: Some(String::from("\"version\": \"")),
-        version_postfix: Some(String::from("\",")),
-        preset: Some(String::from("package.json")),
-    });
+    m.insert(FilePresets::CARGOTOML,
+        preset("Cargo.toml", "Cargo.toml", Some("package.version"), None, None, None));
+    m.insert(FilePresets::PACKAGEJSON,
+        preset("package.json", "package.json", Some("version"), None, None, None));
+
+    // Python packages are written with PEP 440 versions.
+    let pep440 = Some("pep440");
+    m.insert(FilePresets::PYPROJECTTOML,
+        preset("pyproject.toml", "pyproject.toml", Some("project.version"), None, None, pep440));
+    m.insert(FilePresets::POETRY,
+        preset("poetry", "pyproject.toml", Some("tool.poetry.version"), None, None, pep440));
+    m.insert(FilePresets::SETUPCFG,
+        preset("setup.cfg", "setup.cfg", None, Some(r"(?m)^\[metadata\][^\[]*?^version\s*=\s*"), None, pep440));
+    m.insert(FilePresets::SETUPPY,
+        preset("setup.py", "setup.py", None, Some(r#"\bversion\s*=\s*["']"#), Some(r#"["']"#), pep440));
+    // The package directory differs per project, so these presets take the configured path.
+    m.insert(FilePresets::INITPY,
+        preset("__init__.py", "", None, Some(r#"(?m)^__version__\s*=\s*["']"#), Some(r#"["']"#), pep440));
+    m.insert(FilePresets::VERSIONPY,
+        preset("_version.py", "", None, Some(r#"(?m)^__version__\s*=\s*["']"#), Some(r#"["']"#), pep440));
+
+    // Only the project's own version, not the version of its parent or dependencies.
+    m.insert(FilePresets::POMXML,
+        preset("pom.xml", "pom.xml", Some("project.version"), None, None, None));
+    // Top level `version` assignments, the versions of plugins and dependencies are indented or inline.
+    m.insert(FilePresets::GRADLEPROPERTIES,
+        preset("gradle.properties", "gradle.properties", None, Some(r"(?m)^version\s*[=:]\s*"), None, None));
+    m.insert(FilePresets::BUILDGRADLE,
+        preset("build.gradle", "build.gradle", None, Some(r#"(?m)^version\s*=?\s*["']"#), Some(r#"["']"#), None));
+    m.insert(FilePresets::BUILDGRADLEKTS,
+        preset("build.gradle.kts", "build.gradle.kts", None, Some(r#"(?m)^version\s*=\s*""#), Some("\""), None));
+    // Project files are named after the project, so these presets take the configured path.
+    m.insert(FilePresets::CSPROJ,
+        preset("csproj", "", Some("Project.PropertyGroup.Version"), None, None, None));
+    m.insert(FilePresets::CSPROJASSEMBLY,
+        preset("csproj-assembly", "", Some("Project.PropertyGroup.AssemblyVersion"), None, None, Some("assembly")));
+
+    // Top level keys only, the versions of chart dependencies are left alone.
+    m.insert(FilePresets::CHARTYAML,
+        preset("Chart.yaml", "Chart.yaml", Some("version"), None, None, None));
+    m.insert(FilePresets::CHARTAPPVERSION,
+        preset("Chart.yaml-app-version", "Chart.yaml", Some("appVersion"), None, None, None));
+    // Kustomizations also pin third-party images, so the image is selected by a required key.
+    // Image tags cannot contain build metadata.
+    m.insert(FilePresets::KUSTOMIZATION,
+        preset("kustomization.yaml", "kustomization.yaml", None, None, None, Some("docker-tag")));
+    m.insert(FilePresets::DOCKERFILE,
+        preset("Dockerfile", "Dockerfile", None, Some(r#"\borg\.opencontainers\.image\.version=["']?"#), None, None));
+    m.insert(FilePresets::DOCKERFILEARG,
+        preset("Dockerfile-arg", "Dockerfile", None, Some(r#"(?m)^ARG\s+VERSION=["']?"#), None, None));
     m
 });
+
+/// Looks up the preset `name`, presets defined in the configuration take precedence over the built in ones.
+pub fn find_preset<'a>(name: &str, defined: Option<&'a BTreeMap<String, VersionFileConfig>>) -> Result<&'a VersionFileConfig, crate::Error> {
+    if let Some(preset) = defined.and_then(|d| d.get(name)) {
+        if preset.preset.is_some() {
+            return Err(crate::Error::InvalidConfigError{
+                reason: format!("preset {} cannot be based on another preset", name)
+            });
+        }
+        return Ok(preset);
+    }
+    let preset = FilePresets::from_str(name)?;
+    Ok(PRESETS.get(&preset).expect("Preset not part of preset map"))
+}
+
+/// Returns the presets whose file exists at the root of the repository at `repo_path`
+/// and contains a version where the preset expects one, sorted by preset name.
+pub fn detect_presets(repo_path: &str) -> Vec<&'static VersionFileConfig> {
+    let mut found: Vec<&VersionFileConfig> = PRESETS.values()
+        .filter(|p| !p.path.is_empty() && Path::new(repo_path).join(&p.path).is_file())
+        .filter(|p| p.key.is_some() || p.version_prefix.is_some())
+        .filter(|p| VersionFile::new(
+            p.path.clone(),
+            p.key.clone(),
+            p.version_prefix.clone().unwrap_or_default(),
+            p.version_postfix.clone().unwrap_or_default(),
+            p.v.unwrap_or(false),
+        ).is_ok_and(|f| f.read_version(repo_path).is_ok()))
+        .collect();
+    found.sort_by_key(|p| p.preset.clone());
+    found
+}
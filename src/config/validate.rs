@@ -0,0 +1,429 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::presets;
+use super::{ConventionalSemverConfig, VersionFileConfig};
+use crate::changelog::ChangelogFormat;
+use crate::release::{self, VersionFile};
+use crate::release::editor::Occurrences;
+use crate::release::scheme::VersionScheme;
+use crate::release::workspace::{cargo, npm, Ecosystem};
+
+static UNKNOWN_FIELD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^unknown field `([^`]+)`").expect("Invalid unknown field matcher")
+});
+
+static KEY_CONTEXT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r" for key `([^`]+)`").expect("Invalid key context matcher")
+});
+
+/// A problem found in conventional_release.toml, with its 1-based position when known.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, col)) => write!(f, "{}:{}: {}", line, col, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Validates the contents of a conventional_release.toml.
+/// Reports unknown keys, invalid prefix and postfix regexes, unsupported presets,
+/// invalid `[presets.<name>]` tables and version files missing from `repo_path`.
+pub fn validate(source: &str, repo_path: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    let (parsed, unknown) = parse_ignoring_unknown_keys(source);
+    diagnostics.extend(unknown);
+    let config = match parsed {
+        Ok(config) => config,
+        Err(err) => {
+            diagnostics.push(Diagnostic{
+                position: err.line_col().map(|(line, col)| (line + 1, col + 1)),
+                message: err.to_string(),
+            });
+            return diagnostics;
+        },
+    };
+
+    for (i, f) in config.version_files.iter().flatten().enumerate() {
+        let at = |key: &str| key_position(source, "version_files", Some(i), key)
+            .or_else(|| table_position(source, "version_files", Some(i)));
+        let mut resolved = f.clone();
+        if let Some(pre) = &f.preset {
+            match presets::find_preset(pre, config.presets.as_ref()) {
+                Ok(preset) => resolved.apply_preset(preset),
+                Err(e) => {
+                    diagnostics.push(Diagnostic{position: at("preset"), message: e.to_string()});
+                    continue;
+                },
+            }
+            if resolved.path.is_empty() {
+                diagnostics.push(Diagnostic{
+                    position: at("preset"),
+                    message: format!("preset `{}` requires a path", pre),
+                });
+                continue;
+            }
+            if !resolved.has_locator() {
+                diagnostics.push(Diagnostic{
+                    position: at("preset"),
+                    message: format!("preset `{}` requires a key", pre),
+                });
+                continue;
+            }
+        } else if f.path.is_empty() {
+            diagnostics.push(Diagnostic{
+                position: at("path"),
+                message: String::from("version_file path cannot be blank, without a preset"),
+            });
+            continue;
+        }
+
+        check_locator(&resolved, f, &at, &mut diagnostics);
+        if release::is_glob(&resolved.path) {
+            if let Err(e) = release::expand_glob(repo_path, &resolved.path, resolved.allow_no_matches.unwrap_or(false)) {
+                diagnostics.push(Diagnostic{position: at("path"), message: e.to_string()});
+            }
+        } else if !Path::new(repo_path).join(&resolved.path).is_file() {
+            diagnostics.push(Diagnostic{
+                position: at("path"),
+                message: format!("version file {} does not exist", resolved.path),
+            });
+        }
+    }
+
+    for (name, p) in config.presets.iter().flatten() {
+        let table = format!("presets.{}", name);
+        let at = |key: &str| key_position(source, &table, None, key)
+            .or_else(|| table_position(source, &table, None));
+        if p.preset.is_some() {
+            diagnostics.push(Diagnostic{
+                position: at("preset"),
+                message: format!("preset `{}` cannot be based on another preset", name),
+            });
+        }
+        check_locator(p, p, &at, &mut diagnostics);
+    }
+
+    for (i, f) in config.changelog_files.iter().flatten().enumerate() {
+        let at = |key: &str| key_position(source, "changelog_files", Some(i), key)
+            .or_else(|| table_position(source, "changelog_files", Some(i)));
+        match ChangelogFormat::from_str(&f.format) {
+            Err(e) => diagnostics.push(Diagnostic{position: at("format"), message: e.to_string()}),
+            Ok(ChangelogFormat::KeepAChangelog) if !Path::new(repo_path).join(&f.path).is_file() => {
+                diagnostics.push(Diagnostic{
+                    position: at("path"),
+                    message: format!("changelog file {} does not exist", f.path),
+                });
+            },
+            Ok(ChangelogFormat::Debian) if f.package.is_none() => {
+                diagnostics.push(Diagnostic{
+                    position: at("format"),
+                    message: String::from("debian changelogs require a package name"),
+                });
+            },
+            _ => (),
+        }
+    }
+
+    for (i, g) in config.generated_files.iter().flatten().enumerate() {
+        let at = |key: &str| key_position(source, "generated_files", Some(i), key)
+            .or_else(|| table_position(source, "generated_files", Some(i)));
+        match (&g.template, &g.inline) {
+            (Some(_), Some(_)) => diagnostics.push(Diagnostic{
+                position: at("inline"),
+                message: String::from("a generated file takes either a template or an inline template, not both"),
+            }),
+            (None, None) => diagnostics.push(Diagnostic{
+                position: at("path"),
+                message: format!("generated file {} requires either a template or an inline template", g.path),
+            }),
+            (Some(template), None) if !Path::new(repo_path).join(template).is_file() => diagnostics.push(Diagnostic{
+                position: at("template"),
+                message: format!("template {} does not exist", template),
+            }),
+            _ => (),
+        }
+    }
+
+    for (i, w) in config.workspaces.iter().flatten().enumerate() {
+        let at = |key: &str| key_position(source, "workspaces", Some(i), key)
+            .or_else(|| table_position(source, "workspaces", Some(i)));
+        let discovered = match Ecosystem::from_str(&w.ecosystem) {
+            Err(e) => {
+                diagnostics.push(Diagnostic{position: at("ecosystem"), message: e.to_string()});
+                continue;
+            },
+            Ok(Ecosystem::Cargo) => cargo::discover(repo_path, &w.path).map(|_| ()),
+            Ok(Ecosystem::Npm) => npm::discover(repo_path, &w.path).map(|_| ()),
+        };
+        if let Err(e) = discovered {
+            diagnostics.push(Diagnostic{position: at("path"), message: e.to_string()});
+        }
+    }
+    diagnostics
+}
+
+/// Parses a configuration, skipping every unknown key.
+/// Returns a diagnostic per unknown key, along with the configuration or the first other error.
+pub(crate) fn parse_ignoring_unknown_keys(source: &str) -> (Result<ConventionalSemverConfig, toml::de::Error>, Vec<Diagnostic>) {
+    // Errors other than unknown keys keep the position reported by the parser.
+    match toml::from_str::<ConventionalSemverConfig>(source) {
+        Err(err) if UNKNOWN_FIELD.is_match(&err.to_string()) => (),
+        parsed => return (parsed, vec![]),
+    }
+    let mut value = match toml::from_str::<toml::Value>(source) {
+        Ok(value) => value,
+        Err(err) => return (Err(err), vec![]),
+    };
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    // Serde stops at the first unknown key, remove each one from the document to find the next.
+    loop {
+        let err = match value.clone().try_into::<ConventionalSemverConfig>() {
+            Ok(config) => {
+                // Tables are visited in key order, report the keys in the order of the file.
+                diagnostics.sort_by_key(|d| d.position);
+                return (Ok(config), diagnostics);
+            },
+            Err(err) => err,
+        };
+        let message = err.to_string();
+        let field = match UNKNOWN_FIELD.captures(&message) {
+            Some(c) => c[1].to_string(),
+            None => return (Err(err), diagnostics),
+        };
+        let table = KEY_CONTEXT.captures(&message).map(|c| c[1].to_string()).unwrap_or_default();
+        let removed = remove_key(&mut value, &table, &field);
+        if removed.is_empty() {
+            return (Err(err), diagnostics);
+        }
+        let subtable = match table.is_empty() {
+            true => field.clone(),
+            false => format!("{}.{}", table, field),
+        };
+        for index in removed {
+            // Unknown tables, such as `[extra]`, are reported at their header.
+            let position = key_position(source, &table, index, &field)
+                .or_else(|| table_position(source, &subtable, None))
+                .or_else(|| table_position(source, &table, index));
+            diagnostics.push(Diagnostic{position, message: format!("unknown key `{}`", field)});
+        }
+    }
+}
+
+/// Removes `field` from the table at the dotted `table` path, or from every table of an array of tables,
+/// as they all have the same fields. Returns the array index of each table it was removed from.
+fn remove_key(value: &mut toml::Value, table: &str, field: &str) -> Vec<Option<usize>> {
+    let mut node = value;
+    for segment in table.split('.').filter(|s| !s.is_empty()) {
+        node = match node.get_mut(segment) {
+            Some(n) => n,
+            None => return vec![],
+        };
+    }
+    match node {
+        toml::Value::Table(t) => t.remove(field).map(|_| None).into_iter().collect(),
+        toml::Value::Array(items) => items.iter_mut().enumerate()
+            .filter_map(|(i, item)| item.as_table_mut()?.remove(field).map(|_| Some(i)))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Reports a `key` that cannot be used with the file type, invalid regexes, version schemes and occurrences,
+/// and regex settings ignored because of a `key`. `explicit` holds the fields set on the entry itself.
+fn check_locator(
+    resolved: &VersionFileConfig,
+    explicit: &VersionFileConfig,
+    at: &dyn Fn(&str) -> Option<(usize, usize)>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if resolved.key.is_some() {
+        let ignored = [
+            ("version_prefix", resolved.version_prefix.is_some()),
+            ("version_postfix", resolved.version_postfix.is_some()),
+            ("literal", resolved.literal.is_some()),
+            ("occurrences", resolved.occurrences.is_some()),
+        ];
+        for (key, set) in ignored {
+            if set {
+                diagnostics.push(Diagnostic{
+                    position: at(key),
+                    message: format!("`{}` is ignored, the version is located by `key`", key),
+                });
+            }
+        }
+    }
+    // Presets without a path are only checked once a version file gives them one.
+    let checked = match resolved.path.is_empty() && resolved.key.is_some() {
+        true => Ok(()),
+        false => {
+            let (prefix, postfix) = resolved.matcher_parts();
+            VersionFile::new(resolved.path.clone(), resolved.key.clone(), prefix, postfix, resolved.v.unwrap_or(false)).map(|_| ())
+        },
+    };
+    match checked {
+        Err(e @ release::Error::UnsupportedKeyError{..}) => {
+            diagnostics.push(Diagnostic{position: at("key"), message: e.to_string()});
+        },
+        Err(e) => {
+            let key = match (&explicit.version_prefix, &explicit.version_postfix) {
+                (Some(p), _) if !resolved.literal.unwrap_or(false) && Regex::new(p).is_err() => "version_prefix",
+                (_, Some(p)) if !resolved.literal.unwrap_or(false) && Regex::new(p).is_err() => "version_postfix",
+                _ => "preset",
+            };
+            diagnostics.push(Diagnostic{
+                position: at(key),
+                message: format!("invalid version regex: {}", e),
+            });
+        },
+        Ok(_) => (),
+    }
+    if let Some(Err(e)) = resolved.version_scheme.as_deref().map(VersionScheme::from_str) {
+        diagnostics.push(Diagnostic{position: at("version_scheme"), message: e.to_string()});
+    }
+    if let Some(Err(e)) = resolved.occurrences.as_ref().map(Occurrences::try_from) {
+        diagnostics.push(Diagnostic{position: at("occurrences"), message: e.to_string()});
+    }
+}
+
+/// Scans `source` line by line, calling `visit` with the 0-based line number,
+/// the current table name, its array index and the line's key, if any.
+fn scan_keys(source: &str, mut visit: impl FnMut(usize, &str, Option<usize>, Option<(&str, usize)>) -> bool) {
+    let mut table = String::new();
+    let mut index = None;
+    let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for (line_no, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        let col = line.len() - line.trim_start().len();
+        if let Some(name) = trimmed.strip_prefix("[[").and_then(|l| l.split("]]").next()) {
+            table = name.trim().to_string();
+            let count = counts.entry(table.clone()).or_insert(0);
+            index = Some(*count);
+            *count += 1;
+            if visit(line_no, &table, index, None) {
+                return;
+            }
+        } else if let Some(name) = trimmed.strip_prefix('[').and_then(|l| l.split(']').next()) {
+            table = name.trim().to_string();
+            index = None;
+            if visit(line_no, &table, index, None) {
+                return;
+            }
+        } else if let Some((key, _)) = trimmed.split_once('=') {
+            if trimmed.starts_with('#') {
+                continue;
+            }
+            let key = key.split('.').next().unwrap_or_default().trim().trim_matches(|c| c == '"' || c == '\'');
+            if visit(line_no, &table, index, Some((key, col))) {
+                return;
+            }
+        }
+    }
+}
+
+/// 1-based position of `key` in the table `table`, or the `index`th entry of an array of tables.
+fn key_position(source: &str, table: &str, index: Option<usize>, key: &str) -> Option<(usize, usize)> {
+    let mut found = None;
+    scan_keys(source, |line, t, i, k| {
+        if let Some((k, col)) = k {
+            if t == table && i == index && k == key {
+                found = Some((line + 1, col + 1));
+                return true;
+            }
+        }
+        false
+    });
+    found
+}
+
+/// 1-based position of the header of a table, or of the `index`th entry of an array of tables.
+fn table_position(source: &str, table: &str, index: Option<usize>) -> Option<(usize, usize)> {
+    let mut found = None;
+    scan_keys(source, |line, t, i, k| {
+        if k.is_none() && t == table && i == index {
+            found = Some((line + 1, 1));
+            return true;
+        }
+        false
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unknown(source: &str) -> Vec<String> {
+        let (parsed, diagnostics) = parse_ignoring_unknown_keys(source);
+        assert!(parsed.is_ok(), "{:?}", parsed.err());
+        diagnostics.iter().map(Diagnostic::to_string).collect()
+    }
+
+    #[test]
+    fn skips_unknown_tables() {
+        assert_eq!(unknown("v = true\n\n[extra]\nfoo = 1\n"), ["3:1: unknown key `extra`"]);
+    }
+
+    #[test]
+    fn skips_unknown_multi_line_values() {
+        let source = "v = true\nextra = [\n  1,\n  2,\n]\n\n[[version_files]]\npath = \"a.txt\"\nkey = \"version\"\n";
+        let (parsed, diagnostics) = parse_ignoring_unknown_keys(source);
+        assert_eq!(parsed.unwrap().version_files.unwrap()[0].path, "a.txt");
+        assert_eq!(diagnostics.iter().map(Diagnostic::to_string).collect::<Vec<_>>(), ["2:1: unknown key `extra`"]);
+    }
+
+    #[test]
+    fn skips_unknown_keys_of_every_entry() {
+        let source = "[[version_files]]\npath = \"a\"\nfoo = 1\n\n[[version_files]]\npath = \"b\"\nfoo = [\n  1,\n]\n\n[presets.txt]\nbar = \"x\"\n";
+        assert_eq!(unknown(source), [
+            "3:1: unknown key `foo`",
+            "7:1: unknown key `foo`",
+            "12:1: unknown key `bar`",
+        ]);
+    }
+
+    #[test]
+    fn keeps_the_position_of_other_errors() {
+        let (parsed, _) = parse_ignoring_unknown_keys("v = \"yes\"\n");
+        assert!(parsed.unwrap_err().line_col().is_some());
+        let (parsed, _) = parse_ignoring_unknown_keys("v = [\n");
+        assert!(parsed.is_err());
+    }
+
+    fn validated(source: &str) -> Vec<String> {
+        validate(source, env!("CARGO_MANIFEST_DIR")).iter().map(Diagnostic::to_string).collect()
+    }
+
+    #[test]
+    fn points_at_the_invalid_setting() {
+        let source = "[[version_files]]\npath = \"Cargo.toml\"\nversion_prefix = \"(\"\n";
+        let diagnostics = validated(source);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("3:1: invalid version regex"), "{}", diagnostics[0]);
+
+        let source = "[[version_files]]\npath = \"Cargo.toml\"\nkey = \"package.version\"\nliteral = true\n";
+        assert_eq!(validated(source), ["4:1: `literal` is ignored, the version is located by `key`"]);
+    }
+
+    #[test]
+    fn reports_incomplete_presets_and_missing_files() {
+        let source = "[[version_files]]\npreset = \"mine\"\n\n[[version_files]]\npath = \"missing.toml\"\nkey = \"version\"\n\n[presets.mine]\npath = \"Cargo.toml\"\n";
+        assert_eq!(validated(source), [
+            "2:1: preset `mine` requires a key",
+            "5:1: version file missing.toml does not exist",
+        ]);
+        assert_eq!(validated("[[version_files]]\npreset = \"nope\"\n").len(), 1);
+    }
+}
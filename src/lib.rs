@@ -1,5 +1,6 @@
 pub mod release;
 pub mod config;
+pub mod changelog;
 extern crate custom_error;
 
 use std::io;
@@ -19,17 +20,54 @@ custom_error! { pub Error
     InvalidConfigError{reason: String} = "conventional_release.toml is invalid: {reason}"
 }
 
-#[derive(Clone, PartialEq, Eq)]
-enum VersionBump {
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VersionBump {
     MAJOR,
     MINOR,
     PATCH
 }
 
+impl std::fmt::Display for VersionBump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VersionBump::MAJOR => write!(f, "major"),
+            VersionBump::MINOR => write!(f, "minor"),
+            VersionBump::PATCH => write!(f, "patch"),
+        }
+    }
+}
+
+/// Summary of how the version of the repository HEAD was derived.
+#[derive(Debug, Clone)]
+pub struct VersionDetails {
+    pub version: String,
+    /// Most recent release tag before the derived version.
+    pub previous_version: Option<String>,
+    pub bump: VersionBump,
+    /// Commits since the previous release tag.
+    pub commit_count: u32,
+    pub head: Oid,
+    pub dirty: bool,
+    /// HEAD is already tagged with a release version.
+    pub tagged: bool,
+}
+
 struct VersionBumpDetails {
     bump_type: VersionBump,
     current_version: ParsedVersion,
+    previous_tag: Option<String>,
+    previous_tag_id: Option<Oid>,
     rev_count: u32,
+    commits: Vec<Oid>,
+}
+
+/// A commit in the current release that does not follow conventional commits.
+#[derive(Debug, Clone)]
+pub struct LintFailure {
+    pub id: Oid,
+    pub summary: String,
+    pub reason: String,
 }
 
 pub struct ConventionalRepo {
@@ -47,6 +85,11 @@ impl ConventionalRepo {
         })
     }
 
+    /// Version bumped from when no release tag is reachable.
+    fn initial_version(&self) -> &str {
+        self.config.initial_version.as_deref().unwrap_or("0.0.0")
+    }
+
     /// Checks if repo at `repo_path` is dirty.
     /// Returns Error result if unable to locate/open repository at `repo_path`.
     pub fn is_repo_dirty(&self) -> Result<bool, Error> {
@@ -68,25 +111,119 @@ impl ConventionalRepo {
     }
 
     pub fn derive_version(&self, is_release: bool) -> Result<String, Error> {
+        Ok(self.derive_version_details(is_release)?.version)
+    }
+
+    /// Derives the version of HEAD along with the commits and tags it was derived from.
+    pub fn derive_version_details(&self, is_release: bool) -> Result<VersionDetails, Error> {
         let dirty = self.is_repo_dirty()?;
         let head = self.repo.head()?.peel_to_commit()?;
         let head_id = head.as_object().id();
-        match get_revision_tags(&self.repo, head_id) {
+        let head_tags = get_revision_tags(&self.repo, head_id);
+        let tagged = head_tags.is_some();
+        match head_tags {
             Some(versions) if !dirty => {
                 // Head commit is currently tagged, rebuild with highest version.
-                Ok(determine_current_version(versions).original)
+                let details = self.walk_release(&head)?;
+                Ok(VersionDetails{
+                    version: determine_current_version(versions).original,
+                    previous_version: details.previous_tag,
+                    bump: details.bump_type,
+                    commit_count: details.rev_count,
+                    head: head_id,
+                    dirty,
+                    tagged,
+                })
             },
             _ => {
-                let mut version = dervive_next_version(&self.repo, head_id)?;
+                let mut refs = self.repo.revwalk()?;
+                refs.push(head_id)?;
+                let details = derive_version_increase(&self.repo, refs, None, self.initial_version())?;
+                let mut version = dervive_next_version(&details, head_id);
                 // Remove Prerelease and build metadata if releasing.
                 if is_release && !dirty {
                     version.parsed.pre = Prerelease::EMPTY;
                     version.parsed.build = BuildMetadata::EMPTY;
                 }
-                Ok(version.parsed.to_string())
+                Ok(VersionDetails{
+                    version: version.parsed.to_string(),
+                    previous_version: details.previous_tag,
+                    bump: details.bump_type,
+                    commit_count: details.rev_count,
+                    head: head_id,
+                    dirty,
+                    tagged,
+                })
             }
         }
     }
+
+    /// Walks the commits of the release at `head`.
+    /// Tags on `head` itself are ignored, so that a tagged release
+    /// is described rather than the next one.
+    fn walk_release(&self, head: &git2::Commit) -> Result<VersionBumpDetails, Error> {
+        let mut refs = self.repo.revwalk()?;
+        refs.push(head.id())?;
+        derive_version_increase(&self.repo, refs, Some(head.id()), self.initial_version())
+    }
+
+    /// Returns the version of the most recent release tag reachable from HEAD,
+    /// or None if the repository has not been released yet.
+    pub fn get_current_version(&self) -> Result<Option<String>, Error> {
+        if let Some(version) = self.get_head_version() {
+            return Ok(Some(version));
+        }
+        let mut refs = self.repo.revwalk()?;
+        refs.push_head()?;
+        Ok(derive_version_increase(&self.repo, refs, None, self.initial_version())?.previous_tag)
+    }
+
+    /// Checks that every commit since the previous release tag follows conventional commits.
+    /// Merge commits are skipped.
+    pub fn lint_commits(&self) -> Result<Vec<LintFailure>, Error> {
+        let head = self.repo.head()?.peel_to_commit()?;
+        let details = self.walk_release(&head)?;
+        let mut failures = vec![];
+        for oid in details.commits {
+            let commit = self.repo.find_commit(oid)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let message = commit.message().unwrap_or_default();
+            if let Err(e) = git_conventional::Commit::parse(message) {
+                failures.push(LintFailure{
+                    id: oid,
+                    summary: commit.summary().unwrap_or_default().to_string(),
+                    reason: e.to_string(),
+                });
+            }
+        }
+        Ok(failures)
+    }
+
+    /// Builds the release notes for `version`, covering every commit
+    /// since the previous release tag.
+    /// When HEAD is already tagged, the notes describe that tagged release.
+    pub fn release_notes(&self, version: &str) -> Result<changelog::ReleaseNotes, Error> {
+        let head = self.repo.head()?.peel_to_commit()?;
+        let details = self.walk_release(&head)?;
+        let commits = details.commits.iter()
+            .filter_map(|oid| changelog::ChangelogCommit::from_oid(&self.repo, *oid))
+            .collect();
+        let contributors = changelog::collect_contributors(
+            &self.repo, &details.commits, details.previous_tag_id)?;
+        let remote = self.repo.find_remote("origin").ok();
+        let links = changelog::Links::resolve(
+            self.config.links.as_ref(),
+            remote.as_ref().and_then(|r| r.url()));
+        Ok(changelog::ReleaseNotes {
+            version: version.to_string(),
+            previous_version: details.previous_tag,
+            commits,
+            contributors,
+            links,
+        })
+    }
 }
 
 #[derive(Clone)]
@@ -107,13 +244,10 @@ impl ParsedVersion {
     }
 }
 
-/// Walks all commits and returns a prerelease version based on the commits
-/// encountered between the head_id commit and the previous tag.
-fn dervive_next_version(repo: &Repository, head_id: Oid) -> Result<ParsedVersion, Error> {
-    let mut refs = repo.revwalk()?;
-    refs.push(head_id)?;
-    let details = derive_version_increase(&repo, refs)?;
-    let mut version = details.current_version;
+/// Returns a prerelease version based on the commits encountered
+/// between the head_id commit and the previous tag.
+fn dervive_next_version(details: &VersionBumpDetails, head_id: Oid) -> ParsedVersion {
+    let mut version = details.current_version.clone();
     match details.bump_type {
         VersionBump::MAJOR => {
             version.parsed.major += 1;
@@ -129,28 +263,42 @@ fn dervive_next_version(repo: &Repository, head_id: Oid) -> Result<ParsedVersion
     version.parsed.pre = Prerelease::new(&details.rev_count.to_string()).unwrap_or_default();
     let mut oid_str = head_id.to_string();
     let build = &oid_str.as_mut_str()[..7];
-    version.parsed.build = BuildMetadata::new(&build).unwrap_or_default();
-    Ok(version)
+    version.parsed.build = BuildMetadata::new(build).unwrap_or_default();
+    version
 }
 
 /// Checks if the provided Oid is a tagged revision in the Repository.
 /// Returns a list of all the tag names if found.
 fn get_revision_tags(repo: &Repository, oid: Oid) -> Option<Vec<ParsedVersion>> {
     let reg = Regex::new(r"^.*/([vV]?\d+\.\d+\.\d+.*)$").ok()?;
-    let tag_refs = repo.references_glob("refs/tags/*").ok()?;
+    let tag_refs = match repo.references_glob("refs/tags/*") {
+        Ok(refs) => refs,
+        Err(e) => {
+            tracing::warn!(error = %e, "failed to list tags");
+            return None;
+        },
+    };
     let tag_items: Vec<ParsedVersion> = tag_refs.filter_map(does_reference_target_commit(oid))
         .filter_map( |rev| -> Option<ParsedVersion> {
             let tag_version = reg.captures(&rev)?.get(1)?.as_str();
-            let parsed = lenient_semver::parse(tag_version).ok()?;
+            let parsed = match lenient_semver::parse(tag_version) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    tracing::debug!(tag = %rev, error = %e, "ignoring tag with an invalid version");
+                    return None;
+                },
+            };
                 if parsed.pre.is_empty() && parsed.build.is_empty() {
+                   tracing::debug!(tag = %rev, commit = %oid, "discovered release tag");
                    return Some(ParsedVersion{
                         original: tag_version.to_string(),
                         parsed,
                     });
                 }
+                tracing::debug!(tag = %rev, commit = %oid, "ignoring prerelease tag");
                 None
         }).collect();
-    if tag_items.len() > 0 {
+    if !tag_items.is_empty() {
         return Some(tag_items)
     }
     None
@@ -159,8 +307,21 @@ fn get_revision_tags(repo: &Repository, oid: Oid) -> Option<Vec<ParsedVersion>>
 /// Creates and returns a closure for determining if a Reference points to a given Oid/commit id.
 fn does_reference_target_commit(commit_id: Oid) -> impl FnMut(Result<Reference, git2::Error>) -> Option<String> {
     move |ref_res: Result<Reference, git2::Error>| {
-        let reference = ref_res.as_ref().ok()?;
-        let coid = reference.peel_to_commit().ok()?;
+        let reference = match ref_res.as_ref() {
+            Ok(reference) => reference,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to read reference");
+                return None;
+            },
+        };
+        let coid = match reference.peel_to_commit() {
+            Ok(coid) => coid,
+            Err(e) => {
+                tracing::debug!(reference = reference.name().unwrap_or_default(), error = %e,
+                    "reference failed to peel to a commit");
+                return None;
+            },
+        };
         if coid.as_object().id() == commit_id {
             return Some(reference.name()?.to_owned());
         }
@@ -170,23 +331,37 @@ fn does_reference_target_commit(commit_id: Oid) -> impl FnMut(Result<Reference,
 
 /// Determines the version bump based on the conventional commit type.
 /// Crawls the repository refs from the refs HEAD to the most recent tag.
-fn derive_version_increase(repo: &Repository, mut refs: Revwalk) -> Result<VersionBumpDetails, Error> {
+/// Tags on the `released` commit are ignored, to crawl the release it is tagged with.
+/// Without any tag, versions are bumped from `initial_version`.
+fn derive_version_increase(
+    repo: &Repository,
+    mut refs: Revwalk,
+    released: Option<Oid>,
+    initial_version: &str,
+) -> Result<VersionBumpDetails, Error> {
     let mut bump_type = VersionBump::PATCH;
-    let mut current_version = ParsedVersion::new("0.0.0")?;
+    let mut current_version = ParsedVersion::new(initial_version)?;
     let mut rev_count = 0u32;
+    let mut commits = vec![];
 
     while let Some(oid) = refs.next().transpose()? {
-        if let Some(tags) = get_revision_tags(&repo, oid) {
+        if let Some(tags) = get_revision_tags(repo, oid).filter(|_| Some(oid) != released) {
             current_version = determine_current_version(tags);
-            return Ok(VersionBumpDetails{bump_type, current_version, rev_count});
+            let previous_tag = Some(current_version.original.clone());
+            return Ok(VersionBumpDetails{
+                bump_type, current_version, previous_tag, previous_tag_id: Some(oid), rev_count, commits
+            });
         }
+        commits.push(oid);
         bump_type = match derive_version_from_commit(repo, oid, bump_type.clone()) {
             Some(v) => v,
             None => bump_type,
         };
         rev_count += 1;
     }
-    Ok(VersionBumpDetails{bump_type, current_version, rev_count})
+    Ok(VersionBumpDetails{
+        bump_type, current_version, previous_tag: None, previous_tag_id: None, rev_count, commits
+    })
 }
 
 /// From a list of versions, determine the largest or most recent version
@@ -215,7 +390,16 @@ fn derive_version_from_commit(repo: &Repository, commit_oid: Oid, current_bump:
     let obj = repo.find_object(commit_oid, Some(ObjectType::Commit)).ok()?;
     let commit = obj.as_commit()?;
     let commit_msg = commit.message()?;
-    let parsed_commit = git_conventional::Commit::parse(commit_msg).ok()?;
+    let parsed_commit = match git_conventional::Commit::parse(commit_msg) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            tracing::debug!(commit = %commit_oid, error = %e, "commit is not a conventional commit");
+            return None;
+        },
+    };
+    tracing::debug!(commit = %commit_oid, type_ = %parsed_commit.type_(),
+        scope = parsed_commit.scope().map(|s| s.to_string()), breaking = parsed_commit.breaking(),
+        "parsed commit");
     if parsed_commit.breaking() {
         return Some(VersionBump::MAJOR);
     }
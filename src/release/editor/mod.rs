@@ -0,0 +1,398 @@
+use custom_error::custom_error;
+use regex::Regex;
+use toml_edit::DocumentMut;
+
+use super::workspace::{cargo, npm};
+use crate::config::OccurrencesConfig;
+
+mod xml;
+mod yaml;
+
+custom_error! { pub EditError
+    ParseError{reason: String} = "{reason}",
+    MissingVersion = "no version found",
+    NotAString{key: String} = "{key} is not a string",
+    TooFewOccurrences{expected: usize, found: usize} = "expected {expected} occurrences of the version, found {found}",
+}
+
+/// Which matches of a prefix and postfix regex are replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Occurrences {
+    First,
+    All,
+    /// The first N matches, the file must contain at least N.
+    Count(usize),
+}
+
+impl TryFrom<&OccurrencesConfig> for Occurrences {
+    type Error = crate::Error;
+    fn try_from(config: &OccurrencesConfig) -> Result<Occurrences, crate::Error> {
+        match config {
+            OccurrencesConfig::Name(name) if name == "first" => Ok(Occurrences::First),
+            OccurrencesConfig::Name(name) if name == "all" => Ok(Occurrences::All),
+            OccurrencesConfig::Count(n) if *n > 0 => Ok(Occurrences::Count(*n)),
+            _ => Err(crate::Error::InvalidConfigError{
+                reason: format!("unsupported occurrences {}, expected first, all or a positive number", config)
+            }),
+        }
+    }
+}
+
+/// How the version is located in a version file.
+#[derive(Debug)]
+pub enum VersionEditor {
+    /// The text between a prefix and postfix regex.
+    /// Every replaced match keeps its own prefix and postfix.
+    Pattern{matcher: Regex, occurrences: Occurrences},
+    /// A string addressed by a dotted key path, such as `package.version`.
+    /// The rest of the document keeps its formatting.
+    Toml(String),
+    /// A string addressed by a dotted key path, such as `version`.
+    /// Key order, indentation and every other byte of the document are kept.
+    Json(String),
+    /// The text of the element addressed by a dotted path, such as `project.version`.
+    /// Every other byte of the document is kept.
+    Xml(String),
+    /// Every scalar addressed by a dotted key path, such as `version` or `images[name=app].newTag`.
+    /// Comments and every other byte of the document are kept.
+    Yaml(String),
+    /// A Cargo manifest of a workspace, its versions and requirements on the workspace members.
+    CargoManifest(Vec<String>),
+    /// The entries of the workspace members in Cargo.lock.
+    CargoLock(Vec<String>),
+    /// A package.json of a workspace, its version and dependency ranges on the workspace members.
+    NpmManifest(Vec<String>),
+    /// package-lock.json, its root package and the entries of the workspace members' directories.
+    NpmLock{member_dirs: Vec<String>, members: Vec<String>},
+    /// pnpm-lock.yaml, the specifiers of the dependencies on the workspace members.
+    PnpmLock(Vec<String>),
+}
+
+impl VersionEditor {
+    /// Reads the version currently written in `contents`.
+    pub fn read(&self, contents: &str) -> Result<String, EditError> {
+        match self {
+            VersionEditor::Pattern{matcher, ..} => {
+                let cap = matcher.captures(contents).ok_or(EditError::MissingVersion)?;
+                let start = cap.name("conver_prefix").map_or(0, |m| m.end());
+                let end = cap.name("conver_postfix").map_or(contents.len(), |m| m.start());
+                Ok(contents[start..end].to_string())
+            },
+            VersionEditor::Toml(key) => {
+                let doc = parse_toml(contents)?;
+                let mut item = doc.as_item();
+                for seg in key.split('.') {
+                    item = item.get(seg).ok_or(EditError::MissingVersion)?;
+                }
+                item.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| EditError::NotAString{key: key.clone()})
+            },
+            VersionEditor::Json(key) => {
+                let path: Vec<&str> = key.split('.').collect();
+                let (start, end) = locate_json_string(contents, &path)?;
+                Ok(contents[start + 1..end - 1].to_string())
+            },
+            VersionEditor::Xml(key) => {
+                let path: Vec<&str> = key.split('.').collect();
+                let (start, end) = xml::locate_element_text(contents, &path)?;
+                Ok(contents[start..end].to_string())
+            },
+            VersionEditor::Yaml(key) => {
+                let (start, end) = yaml::locate_scalars(contents, key)?[0];
+                Ok(contents[start..end].to_string())
+            },
+            VersionEditor::CargoManifest(_) => cargo::read_manifest_version(contents),
+            VersionEditor::CargoLock(members) => cargo::read_lock_version(contents, members),
+            VersionEditor::NpmManifest(_) | VersionEditor::NpmLock{..} => {
+                let (start, end) = locate_json_string(contents, &["version"])?;
+                Ok(contents[start + 1..end - 1].to_string())
+            },
+            VersionEditor::PnpmLock(_) => Err(EditError::MissingVersion),
+        }
+    }
+
+    /// Returns `contents` with the version replaced by `version`.
+    pub fn replace(&self, contents: &str, version: &str) -> Result<String, EditError> {
+        match self {
+            VersionEditor::Pattern{matcher, occurrences} => replace_matches(contents, matcher, *occurrences, version),
+            VersionEditor::Toml(key) => {
+                let mut doc = parse_toml(contents)?;
+                let mut item = doc.as_item_mut();
+                for seg in key.split('.') {
+                    item = item.get_mut(seg).ok_or(EditError::MissingVersion)?;
+                }
+                let value = item.as_value_mut()
+                    .filter(|v| v.is_str())
+                    .ok_or_else(|| EditError::NotAString{key: key.clone()})?;
+                replace_toml_str(value, version);
+                Ok(doc.to_string())
+            },
+            VersionEditor::Json(key) => {
+                let path: Vec<&str> = key.split('.').collect();
+                replace_json_string(contents, &path, version)
+            },
+            VersionEditor::Xml(key) => {
+                let path: Vec<&str> = key.split('.').collect();
+                xml::replace_element_text(contents, &path, version)
+            },
+            VersionEditor::Yaml(key) => yaml::replace_scalars(contents, key, version),
+            VersionEditor::CargoManifest(members) => cargo::bump_manifest(contents, version, members),
+            VersionEditor::CargoLock(members) => cargo::bump_lock(contents, version, members),
+            VersionEditor::NpmManifest(members) => npm::bump_manifest(contents, version, members),
+            VersionEditor::NpmLock{member_dirs, members} => {
+                npm::bump_package_lock(contents, version, member_dirs, members)
+            },
+            VersionEditor::PnpmLock(members) => Ok(npm::bump_pnpm_lock(contents, version, members)),
+        }
+    }
+}
+
+/// Replaces the version between the prefix and postfix of each match, the matched text itself is never
+/// interpreted as a replacement template.
+fn replace_matches(contents: &str, matcher: &Regex, occurrences: Occurrences, version: &str) -> Result<String, EditError> {
+    let limit = match occurrences {
+        Occurrences::First => 1,
+        Occurrences::All => usize::MAX,
+        Occurrences::Count(n) => n,
+    };
+    let mut out = String::with_capacity(contents.len());
+    let mut last = 0;
+    let mut found = 0;
+    for cap in matcher.captures_iter(contents).take(limit) {
+        let whole = cap.get(0).expect("Capture without a match");
+        let start = cap.name("conver_prefix").map_or(whole.start(), |m| m.end());
+        let end = cap.name("conver_postfix").map_or(whole.end(), |m| m.start());
+        out.push_str(&contents[last..start]);
+        out.push_str(version);
+        last = end;
+        found += 1;
+    }
+    match occurrences {
+        _ if found == 0 => return Err(EditError::MissingVersion),
+        Occurrences::Count(expected) if found < expected => {
+            return Err(EditError::TooFewOccurrences{expected, found});
+        },
+        _ => (),
+    }
+    out.push_str(&contents[last..]);
+    Ok(out)
+}
+
+pub(crate) fn parse_toml(contents: &str) -> Result<DocumentMut, EditError> {
+    contents.parse::<DocumentMut>()
+        .map_err(|e| EditError::ParseError{reason: e.to_string()})
+}
+
+/// Replaces a TOML string, keeping its surrounding whitespace and comments.
+pub(crate) fn replace_toml_str(value: &mut toml_edit::Value, new: &str) {
+    let decor = value.decor().clone();
+    *value = toml_edit::Value::from(new);
+    *value.decor_mut() = decor;
+}
+
+/// Replaces the string at `path` of a JSON document, leaving every other byte untouched.
+pub(crate) fn replace_json_string(contents: &str, path: &[&str], new: &str) -> Result<String, EditError> {
+    let (start, end) = locate_json_string(contents, path)?;
+    Ok(format!("{}\"{}\"{}", &contents[..start], new, &contents[end..]))
+}
+
+/// Byte range, quotes included, of the string at `path` of a JSON document.
+fn locate_json_string(contents: &str, path: &[&str]) -> Result<(usize, usize), EditError> {
+    // Let serde_json report syntax errors, the scanner below only handles valid documents.
+    serde_json::from_str::<serde_json::Value>(contents)
+        .map_err(|e| EditError::ParseError{reason: e.to_string()})?;
+    let mut scanner = JsonScanner{bytes: contents.as_bytes(), pos: 0};
+    match scanner.value(Some(path))? {
+        Some((start, end)) if contents.as_bytes()[start] == b'"' => Ok((start, end)),
+        Some(_) => Err(EditError::NotAString{key: path.join(".")}),
+        None => Err(EditError::MissingVersion),
+    }
+}
+
+/// Walks a valid JSON document, without building it, to find the span of a value.
+struct JsonScanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl JsonScanner<'_> {
+    /// Skips the value at the current position. When `path` is empty, its span is returned,
+    /// otherwise the span of the value at `path` inside of it, if any.
+    fn value(&mut self, path: Option<&[&str]>) -> Result<Option<(usize, usize)>, EditError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let mut found = None;
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(b'}') {
+                        self.pos += 1;
+                        break;
+                    }
+                    let (key_start, key_end) = self.string()?;
+                    // Keys are compared unescaped, `"ver\u0073ion"` is the key `version`.
+                    let name: String = serde_json::from_slice(&self.bytes[key_start..key_end])
+                        .map_err(|e| EditError::ParseError{reason: e.to_string()})?;
+                    self.skip_whitespace();
+                    self.expect(b':')?;
+                    let child = match path {
+                        Some([seg, rest @ ..]) if found.is_none() && *seg == name => Some(rest),
+                        _ => None,
+                    };
+                    if let Some(span) = self.value(child)? {
+                        found = Some(span);
+                    }
+                    self.skip_whitespace();
+                    if self.peek() == Some(b',') {
+                        self.pos += 1;
+                    }
+                }
+            },
+            Some(b'[') => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(b']') {
+                        self.pos += 1;
+                        break;
+                    }
+                    self.value(None)?;
+                    self.skip_whitespace();
+                    if self.peek() == Some(b',') {
+                        self.pos += 1;
+                    }
+                }
+            },
+            Some(b'"') => {
+                self.string()?;
+            },
+            Some(_) => {
+                while self.peek().is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace()) {
+                    self.pos += 1;
+                }
+            },
+            None => return Err(EditError::ParseError{reason: String::from("unexpected end of document")}),
+        }
+        match path {
+            Some([]) => Ok(Some((start, self.pos))),
+            _ => Ok(found),
+        }
+    }
+
+    /// Skips a string, returning its span with the quotes.
+    fn string(&mut self) -> Result<(usize, usize), EditError> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        while let Some(b) = self.peek() {
+            self.pos += 1;
+            match b {
+                b'\\' => self.pos += 1,
+                b'"' => return Ok((start, self.pos)),
+                _ => (),
+            }
+        }
+        Err(EditError::ParseError{reason: String::from("unterminated string")})
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), EditError> {
+        if self.peek() != Some(byte) {
+            return Err(EditError::ParseError{reason: format!("expected `{}` at byte {}", byte as char, self.pos)});
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(key: &str) -> VersionEditor {
+        VersionEditor::Json(key.to_string())
+    }
+
+    #[test]
+    fn toml_replaces_only_the_addressed_key() {
+        let contents = "[package]\nname = \"app\"\nversion = \"1.0.0\" # bumped\n\n[dependencies]\nserde = { version = \"1.0.0\", features = [\"derive\"] }\n";
+        let editor = VersionEditor::Toml(String::from("package.version"));
+        assert_eq!(editor.read(contents).unwrap(), "1.0.0");
+        assert_eq!(editor.replace(contents, "1.1.0").unwrap(), contents.replacen("\"1.0.0\" # bumped", "\"1.1.0\" # bumped", 1));
+        assert!(matches!(VersionEditor::Toml(String::from("package.name.x")).read(contents), Err(EditError::MissingVersion)));
+    }
+
+    #[test]
+    fn json_replaces_only_the_addressed_key() {
+        let contents = "{\n  \"dependencies\": {\"version\": \"2.0.0\"},\n  \"version\": \"1.0.0\"\n}\n";
+        assert_eq!(json("version").read(contents).unwrap(), "1.0.0");
+        assert_eq!(
+            json("version").replace(contents, "1.1.0").unwrap(),
+            "{\n  \"dependencies\": {\"version\": \"2.0.0\"},\n  \"version\": \"1.1.0\"\n}\n",
+        );
+        assert_eq!(json("dependencies.version").read(contents).unwrap(), "2.0.0");
+    }
+
+    #[test]
+    fn json_skips_escaped_quotes() {
+        let contents = r#"{"description": "set \"version\": \"9.9.9\"", "a\"version": "8.8.8", "version": "1.0.0"}"#;
+        assert_eq!(
+            json("version").replace(contents, "1.1.0").unwrap(),
+            r#"{"description": "set \"version\": \"9.9.9\"", "a\"version": "8.8.8", "version": "1.1.0"}"#,
+        );
+    }
+
+    #[test]
+    fn json_matches_escaped_keys() {
+        let contents = r#"{"ver\u0073ion": "1.0.0"}"#;
+        assert_eq!(json("version").read(contents).unwrap(), "1.0.0");
+        assert_eq!(json("version").replace(contents, "1.1.0").unwrap(), r#"{"ver\u0073ion": "1.1.0"}"#);
+    }
+
+    #[test]
+    fn json_rejects_non_strings() {
+        assert!(matches!(json("version").read(r#"{"version": 1}"#), Err(EditError::NotAString{..})));
+        assert!(matches!(json("version").read(r#"{"name": "app"}"#), Err(EditError::MissingVersion)));
+        assert!(matches!(json("version").read(r#"{"version": "1.0.0""#), Err(EditError::ParseError{..})));
+    }
+
+    #[test]
+    fn pattern_replaces_the_configured_occurrences() {
+        let matcher = Regex::new(r#"(?P<conver_prefix>v=")\d+\.\d+\.\d+(?P<conver_postfix>")"#).unwrap();
+        let contents = r#"v="1.0.0" v="1.0.0" v="1.0.0""#;
+        assert_eq!(
+            replace_matches(contents, &matcher, Occurrences::First, "2.0.0").unwrap(),
+            r#"v="2.0.0" v="1.0.0" v="1.0.0""#,
+        );
+        assert_eq!(
+            replace_matches(contents, &matcher, Occurrences::Count(2), "2.0.0").unwrap(),
+            r#"v="2.0.0" v="2.0.0" v="1.0.0""#,
+        );
+        assert!(matches!(
+            replace_matches(contents, &matcher, Occurrences::Count(4), "2.0.0"),
+            Err(EditError::TooFewOccurrences{expected: 4, found: 3}),
+        ));
+    }
+
+    #[test]
+    fn pattern_keeps_each_occurrence_prefix_and_postfix() {
+        let matcher = Regex::new(r"(?P<conver_prefix>(app|\$lib): )\d+\.\d+\.\d+(?P<conver_postfix>( \$1)?)").unwrap();
+        let contents = "app: 1.0.0\n$lib: 1.0.0 $1\n";
+        assert_eq!(
+            replace_matches(contents, &matcher, Occurrences::All, "2.0.0").unwrap(),
+            "app: 2.0.0\n$lib: 2.0.0 $1\n",
+        );
+        assert!(matches!(replace_matches("none", &matcher, Occurrences::All, "2.0.0"), Err(EditError::MissingVersion)));
+    }
+}
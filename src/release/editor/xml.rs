@@ -0,0 +1,142 @@
+use super::EditError;
+
+/// Byte range of the text of the first element at `path`, surrounding whitespace excluded.
+/// Elements are compared by local name, so namespace prefixes are ignored.
+/// `project.version` finds the version of a Maven project, but not the version of its parent or dependencies.
+pub(crate) fn locate_element_text(contents: &str, path: &[&str]) -> Result<(usize, usize), EditError> {
+    let mut stack: Vec<&str> = vec![];
+    let mut text_start = None;
+    let mut pos = 0;
+    while let Some(offset) = contents[pos..].find('<') {
+        let open = pos + offset;
+        let rest = &contents[open..];
+        if rest.starts_with("<!--") {
+            pos = open + find_end(rest, "-->")?;
+            continue;
+        }
+        if rest.starts_with("<![CDATA[") {
+            pos = open + find_end(rest, "]]>")?;
+            continue;
+        }
+        if rest.starts_with("<?") || rest.starts_with("<!") {
+            pos = open + find_end(rest, ">")?;
+            continue;
+        }
+        let close = open + tag_end(rest)?;
+        let tag = &contents[open + 1..close - 1];
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = local_name(name.trim());
+            if stack.last() != Some(&name) {
+                return Err(EditError::ParseError{reason: format!("unexpected closing tag </{}> at byte {}", name, open)});
+            }
+            if stack == path {
+                let start = text_start.unwrap_or(open);
+                let text = &contents[start..open];
+                let leading = text.len() - text.trim_start().len();
+                let trailing = text.len() - text.trim_end().len();
+                return Ok((start + leading, open - trailing));
+            }
+            stack.pop();
+        } else {
+            let self_closing = tag.ends_with('/');
+            let name = local_name(tag.trim_end_matches('/').split(|c: char| c.is_whitespace()).next().unwrap_or_default());
+            if !self_closing {
+                stack.push(name);
+                if stack == path {
+                    text_start = Some(close);
+                }
+            } else if stack.len() + 1 == path.len() && stack[..] == path[..stack.len()] && name == path[stack.len()] {
+                // `<version/>` has no text to replace.
+                return Err(EditError::NotAString{key: path.join(".")});
+            }
+        }
+        // Nested elements inside the target are not supported.
+        if text_start.is_some() && stack.len() > path.len() {
+            return Err(EditError::NotAString{key: path.join(".")});
+        }
+        pos = close;
+    }
+    Err(EditError::MissingVersion)
+}
+
+/// Replaces the text of the first element at `path`, leaving every other byte untouched.
+pub(crate) fn replace_element_text(contents: &str, path: &[&str], new: &str) -> Result<String, EditError> {
+    let (start, end) = locate_element_text(contents, path)?;
+    Ok(format!("{}{}{}", &contents[..start], new, &contents[end..]))
+}
+
+fn find_end(rest: &str, terminator: &str) -> Result<usize, EditError> {
+    rest.find(terminator)
+        .map(|i| i + terminator.len())
+        .ok_or_else(|| EditError::ParseError{reason: format!("missing `{}`", terminator)})
+}
+
+/// Offset just past the `>` of the tag at the start of `rest`, skipping quoted attribute values.
+fn tag_end(rest: &str) -> Result<usize, EditError> {
+    let mut quote = None;
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Ok(i + 1),
+            _ => (),
+        }
+    }
+    Err(EditError::ParseError{reason: String::from("unterminated tag")})
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POM: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- <project><version>0.0.1</version></project> -->
+<project xmlns="http://maven.apache.org/POM/4.0.0">
+  <parent>
+    <version>3.0.0</version>
+  </parent>
+  <description><![CDATA[<version>9.9.9</version>]]></description>
+  <version attr="a>b"> 1.0.0 </version>
+  <dependencies>
+    <dependency><version>2.0.0</version></dependency>
+  </dependencies>
+</project>
+"#;
+
+    #[test]
+    fn locates_the_element_at_the_path() {
+        let (start, end) = locate_element_text(POM, &["project", "version"]).unwrap();
+        assert_eq!(&POM[start..end], "1.0.0");
+        let (start, end) = locate_element_text(POM, &["project", "parent", "version"]).unwrap();
+        assert_eq!(&POM[start..end], "3.0.0");
+    }
+
+    #[test]
+    fn replaces_only_the_element_text() {
+        let replaced = replace_element_text(POM, &["project", "version"], "1.1.0").unwrap();
+        assert_eq!(replaced, POM.replace("> 1.0.0 <", "> 1.1.0 <"));
+    }
+
+    #[test]
+    fn ignores_namespace_prefixes() {
+        let contents = "<m:project xmlns:m=\"urn:m\"><m:version>1.0.0</m:version></m:project>";
+        let (start, end) = locate_element_text(contents, &["project", "version"]).unwrap();
+        assert_eq!(&contents[start..end], "1.0.0");
+    }
+
+    #[test]
+    fn rejects_elements_without_text() {
+        let nested = "<project><version><major>1</major></version></project>";
+        assert!(matches!(locate_element_text(nested, &["project", "version"]), Err(EditError::NotAString{..})));
+        let empty = "<project><version/></project>";
+        assert!(matches!(locate_element_text(empty, &["project", "version"]), Err(EditError::NotAString{..})));
+        let missing = "<project><name>app</name></project>";
+        assert!(matches!(locate_element_text(missing, &["project", "version"]), Err(EditError::MissingVersion)));
+        let unbalanced = "<project><version>1.0.0</name></project>";
+        assert!(matches!(locate_element_text(unbalanced, &["project", "version"]), Err(EditError::ParseError{..})));
+    }
+}
@@ -0,0 +1,231 @@
+use super::EditError;
+
+/// A key or sequence item of a block style YAML document.
+struct Entry<'a> {
+    indent: usize,
+    /// Starts a sequence item, its key if any is a separate entry.
+    item: bool,
+    key: Option<&'a str>,
+    /// Byte range of the scalar value on the line, quotes excluded.
+    value: Option<(usize, usize)>,
+    text: Option<&'a str>,
+}
+
+/// Byte ranges of the scalars at the dotted `path` of a block style YAML document.
+/// A segment such as `images[name=acme/widget]` selects the items of a sequence by one of their keys,
+/// a segment without a selector walks every item of a sequence.
+/// Keys are only matched at their own level, so `version` never matches `dependencies.version`.
+pub(crate) fn locate_scalars(contents: &str, path: &str) -> Result<Vec<(usize, usize)>, EditError> {
+    let entries = parse(contents);
+    let segments = split_path(path);
+    let spans = find(&entries, &segments, path)?;
+    match spans.is_empty() {
+        true => Err(EditError::MissingVersion),
+        false => Ok(spans),
+    }
+}
+
+/// Replaces the scalars at `path`, leaving every other byte untouched.
+pub(crate) fn replace_scalars(contents: &str, path: &str, new: &str) -> Result<String, EditError> {
+    let mut out = contents.to_string();
+    for (start, end) in locate_scalars(contents, path)?.into_iter().rev() {
+        out.replace_range(start..end, new);
+    }
+    Ok(out)
+}
+
+/// Splits a dotted path on the dots outside of selectors, so `images[name=ghcr.io/acme/app].newTag` has two segments.
+fn split_path(path: &str) -> Vec<&str> {
+    let mut segments = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in path.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            '.' if depth == 0 => {
+                segments.push(&path[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    segments.push(&path[start..]);
+    segments
+}
+
+fn parse(contents: &str) -> Vec<Entry<'_>> {
+    let mut entries = vec![];
+    let mut offset = 0;
+    for line in contents.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let text = line.trim_end_matches(['\n', '\r']);
+        let mut indent = text.len() - text.trim_start_matches(' ').len();
+        let mut rest = &text[indent..];
+        if rest.is_empty() || rest.starts_with('#') || rest.starts_with("---") || rest.starts_with("...") {
+            continue;
+        }
+        if rest == "-" || rest.starts_with("- ") {
+            entries.push(Entry{indent, item: true, key: None, value: None, text: None});
+            let inner = rest[1..].trim_start_matches(' ');
+            indent += rest.len() - inner.len();
+            rest = inner;
+            if rest.is_empty() {
+                continue;
+            }
+        }
+        if let Some((key, value)) = split_key(rest) {
+            let value_start = start + indent + (rest.len() - value.len());
+            let value = scalar(value, value_start);
+            let text = value.map(|(s, e)| &contents[s..e]);
+            entries.push(Entry{indent, item: false, key: Some(key), value, text});
+        }
+    }
+    entries
+}
+
+/// Splits `key: value`, the key without quotes.
+fn split_key(rest: &str) -> Option<(&str, &str)> {
+    let colon = rest.char_indices()
+        .find(|&(i, c)| c == ':' && rest[i + 1..].chars().next().is_none_or(|n| n == ' '))?
+        .0;
+    let key = rest[..colon].trim().trim_matches(|c| c == '"' || c == '\'');
+    Some((key, &rest[colon + 1..]))
+}
+
+/// Range of a plain or quoted scalar, without any trailing comment.
+fn scalar(value: &str, value_start: usize) -> Option<(usize, usize)> {
+    let lead = value.len() - value.trim_start().len();
+    let trimmed = value.trim_start();
+    let start = value_start + lead;
+    match trimmed.chars().next()? {
+        q @ ('"' | '\'') => trimmed[1..].find(q).map(|end| (start + 1, start + 1 + end)),
+        // Flow collections, block scalars, anchors and aliases are not versions.
+        '[' | '{' | '|' | '>' | '&' | '*' | '#' => None,
+        _ => {
+            let plain = trimmed.split(" #").next().unwrap_or(trimmed).trim_end();
+            Some((start, start + plain.len()))
+        },
+    }
+}
+
+fn find(entries: &[Entry], segments: &[&str], path: &str) -> Result<Vec<(usize, usize)>, EditError> {
+    let (segment, rest) = match segments.split_first() {
+        Some(s) => s,
+        None => return Ok(vec![]),
+    };
+    let (name, selector) = match segment.split_once('[') {
+        Some((name, sel)) => (name, sel.strip_suffix(']').and_then(|s| s.split_once('='))),
+        None => (*segment, None),
+    };
+    let level = match entries.iter().find(|e| !e.item) {
+        Some(e) => e.indent,
+        None => return Ok(vec![]),
+    };
+
+    let position = entries.iter().position(|e| !e.item && e.indent == level && e.key == Some(name));
+    let index = match position {
+        Some(i) => i,
+        None => return Ok(vec![]),
+    };
+    let key = &entries[index];
+    // Children are indented further, sequences may start at the key's own indentation.
+    let end = entries[index + 1..].iter()
+        .position(|e| e.indent < key.indent || (e.indent == key.indent && !e.item))
+        .map_or(entries.len(), |p| index + 1 + p);
+    let children = &entries[index + 1..end];
+
+    if rest.is_empty() && selector.is_none() {
+        return match (key.value, children.is_empty()) {
+            (Some(span), true) => Ok(vec![span]),
+            _ => Err(EditError::NotAString{key: path.to_string()}),
+        };
+    }
+    if !children.first().is_some_and(|e| e.item) {
+        return match selector {
+            Some(_) => Ok(vec![]),
+            None => find(children, rest, path),
+        };
+    }
+
+    let item_indent = children[0].indent;
+    let starts: Vec<usize> = children.iter().enumerate()
+        .filter(|(_, e)| e.item && e.indent == item_indent)
+        .map(|(i, _)| i)
+        .collect();
+    let mut spans = vec![];
+    for (n, &item_start) in starts.iter().enumerate() {
+        let item_end = starts.get(n + 1).copied().unwrap_or(children.len());
+        let item = &children[item_start + 1..item_end];
+        if let Some((field, expected)) = selector {
+            let level = item.iter().find(|e| !e.item).map(|e| e.indent);
+            let matched = item.iter()
+                .any(|e| Some(e.indent) == level && e.key == Some(field) && e.text == Some(expected));
+            if !matched {
+                continue;
+            }
+        }
+        spans.extend(find(item, rest, path)?);
+    }
+    Ok(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: &str = "\
+# version: 0.0.1
+version: 1.0.0 # the chart version
+image:
+  repository: acme/app
+  tag: \"1.0.0\"
+dependencies:
+  - name: redis
+    version: 2.0.0
+";
+
+    const KUSTOMIZATION: &str = "\
+images:
+- name: ghcr.io/acme/app
+  newTag: '1.0.0'
+- name: redis
+  newTag: 7.2.0
+";
+
+    fn located<'a>(contents: &'a str, path: &str) -> Vec<&'a str> {
+        locate_scalars(contents, path).unwrap().into_iter().map(|(s, e)| &contents[s..e]).collect()
+    }
+
+    #[test]
+    fn locates_keys_at_their_own_level() {
+        assert_eq!(located(VALUES, "version"), ["1.0.0"]);
+        assert_eq!(located(VALUES, "image.tag"), ["1.0.0"]);
+        assert_eq!(located(VALUES, "dependencies.version"), ["2.0.0"]);
+        assert!(matches!(locate_scalars(VALUES, "image.version"), Err(EditError::MissingVersion)));
+        assert!(matches!(locate_scalars(VALUES, "image"), Err(EditError::NotAString{..})));
+    }
+
+    #[test]
+    fn replaces_keeping_quotes_and_comments() {
+        let replaced = replace_scalars(VALUES, "image.tag", "1.1.0").unwrap();
+        assert_eq!(replaced, VALUES.replace("tag: \"1.0.0\"", "tag: \"1.1.0\""));
+        let replaced = replace_scalars(VALUES, "version", "1.1.0").unwrap();
+        assert_eq!(replaced, VALUES.replace("version: 1.0.0 #", "version: 1.1.0 #"));
+    }
+
+    #[test]
+    fn selects_sequence_items() {
+        assert_eq!(located(KUSTOMIZATION, "images.newTag"), ["1.0.0", "7.2.0"]);
+        assert_eq!(located(KUSTOMIZATION, "images[name=redis].newTag"), ["7.2.0"]);
+        assert_eq!(located(KUSTOMIZATION, "images[name=ghcr.io/acme/app].newTag"), ["1.0.0"]);
+        assert!(matches!(locate_scalars(KUSTOMIZATION, "images[name=acme/app].newTag"), Err(EditError::MissingVersion)));
+    }
+
+    #[test]
+    fn splits_paths_outside_selectors() {
+        assert_eq!(split_path("images[name=ghcr.io/acme/app].newTag"), ["images[name=ghcr.io/acme/app]", "newTag"]);
+        assert_eq!(split_path("a.b.c"), ["a", "b", "c"]);
+    }
+}
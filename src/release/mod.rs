@@ -4,18 +4,40 @@ use std::num::TryFromIntError;
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::Path;
+use std::str::FromStr;
 use git2::{Signature, Oid};
 use regex::Regex;
 use git2::Commit;
 
+use crate::changelog::{self, ChangelogFormat, PackageOptions, ReleaseNotes};
 use crate::config::ConventionalSemverConfig;
 use crate::ConventionalRepo;
 
+pub mod editor;
+pub mod plan;
+pub mod scheme;
+pub mod template;
+pub mod workspace;
+
+use editor::{EditError, Occurrences, VersionEditor};
+use scheme::VersionScheme;
+
 custom_error! { pub Error
     VersionFileError{source: io::Error, file: String} = "Version file error({file}): {source}.",
     VersionMatchError{file: String} = "Unable find version in version file {file}",
+    VersionEditError{source: EditError, file: String} = "Unable to edit version file {file}: {source}",
+    VersionPatternError{source: regex::Error} = "Invalid version_prefix or version_postfix: {source}",
+    UnsupportedKeyError{file: String} = "Version file {file} must be a TOML, JSON, XML or YAML file to use a key",
+    VersionGlobError{pattern: String, reason: String} = "Invalid version file pattern {pattern}: {reason}",
+    NoMatchError{pattern: String} = "Version file pattern {pattern} matches no file",
+    ChangelogError{source: changelog::Error, file: String} = "Changelog file error({file}): {source}.",
+    TemplateError{source: semver::Error, file: String} = "Unable to render generated file {file}: {source}",
     SignatureError{source: TryFromIntError} = "Encountered error when attempting to create git signature timpstamp {source}",
     GitError{source: git2::Error} = "An error occurred when performing a Git action: {source}",
+    StageError{source: git2::Error, file: String} = "Unable to stage release file {file}: {source}",
+    ReleasePathError{file: String} = "Release file {file} must be a relative path inside the repository",
+    PlanError{reason: String} = "Release plan error: {reason}",
+    WorkspaceError{reason: String} = "Workspace error: {reason}",
 }
 
 static SEMVER_MATCHER: &str = r"[vV]?\d+\.\d+\.\d+[-+\w\.]*";
@@ -23,116 +45,575 @@ static SEMVER_MATCHER: &str = r"[vV]?\d+\.\d+\.\d+[-+\w\.]*";
 #[derive(Debug)]
 pub struct VersionFile {
     relative_path: String,
-    matcher: Regex,
+    editor: VersionEditor,
     v: bool,
+    scheme: VersionScheme,
 }
 impl VersionFile {
-    pub fn new(path: String, version_prefix: String, version_postfix: String, v: bool) -> Result<Self, regex::Error> {
-        let regex = construct_matcher(version_prefix, version_postfix)?;
+    /// Files with a `key` are edited as TOML, JSON, XML or YAML, depending on their extension,
+    /// any other file by its `version_prefix` and `version_postfix` regex.
+    pub fn new(path: String, key: Option<String>, version_prefix: String, version_postfix: String, v: bool) -> Result<Self, Error> {
+        let editor = match key {
+            Some(key) => match Path::new(&path).extension().and_then(|e| e.to_str()) {
+                Some("toml") => VersionEditor::Toml(key),
+                Some("json") => VersionEditor::Json(key),
+                Some("xml" | "csproj" | "fsproj" | "vbproj" | "props") => VersionEditor::Xml(key),
+                Some("yaml" | "yml") => VersionEditor::Yaml(key),
+                _ => return Err(Error::UnsupportedKeyError{file: path}),
+            },
+            None => VersionEditor::Pattern{
+                matcher: construct_matcher(version_prefix, version_postfix)?,
+                occurrences: Occurrences::All,
+            },
+        };
         Ok(VersionFile{
             relative_path: path,
-            matcher: regex,
+            editor,
             v,
+            scheme: VersionScheme::Semver,
         })
     }
 
-    pub fn config_to_version_files(config: &ConventionalSemverConfig) -> anyhow::Result<Vec<VersionFile>> {
-        match &config.version_files {
+    /// Writes versions in `scheme` instead of as semantic versions.
+    pub fn with_scheme(mut self, scheme: VersionScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// Replaces only the `occurrences` of the prefix and postfix regex, files with a key have a single version.
+    pub fn with_occurrences(mut self, occurrences: Occurrences) -> Self {
+        if let VersionEditor::Pattern{occurrences: o, ..} = &mut self.editor {
+            *o = occurrences;
+        }
+        self
+    }
+
+    /// Path of the file, relative to the repository.
+    pub fn relative_path(&self) -> &str {
+        &self.relative_path
+    }
+
+    /// The text written for `version`, without any leading v, in this file's scheme.
+    pub fn file_version(&self, version: &str) -> String {
+        match self.v {
+            true => format!("v{}", self.scheme.format(version)),
+            false => self.scheme.format(version),
+        }
+    }
+
+    /// Reads the version currently written in the file.
+    pub fn read_version(&self, repo_path: &str) -> Result<String, Error> {
+        let str_pth = format!("{}/{}", repo_path, self.relative_path);
+        let contents = std::fs::read_to_string(&str_pth)
+            .map_err(|e| Error::VersionFileError{source: e, file: self.relative_path.clone()})?;
+        self.editor.read(&contents).map_err(|e| self.edit_error(e))
+    }
+
+    fn edit_error(&self, e: EditError) -> Error {
+        match e {
+            EditError::MissingVersion => Error::VersionMatchError{file: self.relative_path.clone()},
+            e => Error::VersionEditError{source: e, file: self.relative_path.clone()},
+        }
+    }
+
+    /// Version files of the configuration, followed by the files of every workspace.
+    /// Files edited by a workspace are not also edited as a plain version file.
+    pub fn config_to_version_files(config: &ConventionalSemverConfig, repo_path: &str) -> anyhow::Result<Vec<VersionFile>> {
+        let workspace_files = workspace::config_to_workspace_files(config, repo_path)?;
+        let mut files = Self::config_to_plain_version_files(config, repo_path)?;
+        files.retain(|f| !workspace_files.iter().any(|w| w.relative_path == f.relative_path));
+        files.extend(workspace_files);
+        Ok(files)
+    }
+
+    /// Version files of the configuration, a glob `path` gives a version file for every matched file.
+    fn config_to_plain_version_files(config: &ConventionalSemverConfig, repo_path: &str) -> anyhow::Result<Vec<VersionFile>> {
+        let mut files = vec![];
+        for v_file in config.version_files.iter().flatten() {
+            let scheme = match &v_file.version_scheme {
+                Some(s) => VersionScheme::from_str(s)?,
+                None => VersionScheme::Semver,
+            };
+            let paths = match is_glob(&v_file.path) {
+                true => expand_glob(repo_path, &v_file.path, v_file.allow_no_matches.unwrap_or(false))?,
+                false => vec![v_file.path.clone()],
+            };
+            let occurrences = match &v_file.occurrences {
+                Some(o) => Occurrences::try_from(o)?,
+                None => Occurrences::All,
+            };
+            let (prefix, postfix) = v_file.matcher_parts();
+            for path in paths {
+                files.push(VersionFile::new(
+                    path,
+                    v_file.key.clone(),
+                    prefix.clone(),
+                    postfix.clone(),
+                    v_file.v.unwrap_or(false)
+                )?.with_scheme(scheme).with_occurrences(occurrences));
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Whether a version file path is a glob pattern, such as `charts/*/Chart.yaml`.
+pub fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Files matching the glob `pattern`, relative to and sorted below the repository at `repo_path`.
+/// No match is an error, unless `allow_no_matches` is set.
+pub fn expand_glob(repo_path: &str, pattern: &str, allow_no_matches: bool) -> Result<Vec<String>, Error> {
+    let base = Path::new(repo_path).canonicalize()
+        .map_err(|e| Error::VersionFileError{source: e, file: repo_path.to_string()})?;
+    let full = format!("{}/{}", glob::Pattern::escape(&base.to_string_lossy()), pattern);
+    let paths = glob::glob(&full).map_err(|e| Error::VersionGlobError{
+        pattern: pattern.to_string(),
+        reason: e.to_string(),
+    })?;
+    let matched: Vec<String> = paths.flatten()
+        .filter(|p| p.is_file())
+        .filter_map(|p| p.strip_prefix(&base).ok().map(|r| r.to_string_lossy().to_string()))
+        .collect();
+    if matched.is_empty() && !allow_no_matches {
+        return Err(Error::NoMatchError{pattern: pattern.to_string()});
+    }
+    tracing::debug!(pattern, matches = matched.len(), "expanded version file pattern");
+    Ok(matched)
+}
+
+#[derive(Debug)]
+pub struct ChangelogFile {
+    relative_path: String,
+    format: ChangelogFormat,
+    options: PackageOptions,
+}
+impl ChangelogFile {
+    pub fn new(path: String, format: ChangelogFormat, options: PackageOptions) -> Self {
+        ChangelogFile{
+            relative_path: path,
+            format,
+            options,
+        }
+    }
+
+    pub fn config_to_changelog_files(config: &ConventionalSemverConfig) -> anyhow::Result<Vec<ChangelogFile>> {
+        match &config.changelog_files {
             None => Ok(vec![]),
-            Some(version_files) => {
-                version_files.iter().map(|v_file| -> anyhow::Result<VersionFile> {
-                    Ok(VersionFile::new(
-                        v_file.path.clone(),
-                        v_file.version_prefix.as_ref()
-                            .unwrap_or(&String::from("")).clone(),
-                        v_file.version_postfix.as_ref()
-                            .unwrap_or(&String::from("")).clone(),
-                        v_file.v
-                    )?)
+            Some(changelog_files) => {
+                changelog_files.iter().map(|c_file| -> anyhow::Result<ChangelogFile> {
+                    let sig = &config.commit_signature;
+                    Ok(ChangelogFile::new(
+                        c_file.path.clone(),
+                        ChangelogFormat::from_str(&c_file.format)?,
+                        PackageOptions {
+                            package: c_file.package.clone(),
+                            distribution: c_file.distribution.clone(),
+                            urgency: c_file.urgency.clone(),
+                            maintainer: c_file.maintainer.clone()
+                                .unwrap_or_else(|| format!("{} <{}>", sig.name, sig.email)),
+                            revision: c_file.revision.clone(),
+                        },
+                    ))
                 }).collect()
             }
         }
     }
 }
 
+/// Where the template of a generated file comes from.
+#[derive(Debug)]
+enum TemplateSource {
+    /// A template file, relative to the repository.
+    File(String),
+    Inline(String),
+}
+
+/// A file rendered from a template on release, such as `src/version.rs`.
+#[derive(Debug)]
+pub struct GeneratedFile {
+    relative_path: String,
+    template: TemplateSource,
+}
+impl GeneratedFile {
+    pub fn config_to_generated_files(config: &ConventionalSemverConfig) -> anyhow::Result<Vec<GeneratedFile>> {
+        config.generated_files.iter().flatten().map(|g_file| -> anyhow::Result<GeneratedFile> {
+            let template = match (&g_file.template, &g_file.inline) {
+                (Some(path), None) => TemplateSource::File(path.clone()),
+                (None, Some(inline)) => TemplateSource::Inline(inline.clone()),
+                _ => return Err(crate::Error::InvalidConfigError{
+                    reason: format!("generated file {} requires either a template or an inline template", g_file.path)
+                }.into()),
+            };
+            Ok(GeneratedFile{relative_path: g_file.path.clone(), template})
+        }).collect()
+    }
+}
+
+/// Renders every generated file for `version`. `sha` is HEAD before the release commit is created,
+/// so the parent of the tagged release commit.
+/// Files that don't exist yet are created.
+pub fn compute_generated_files(repo_path: &str, version: &str, sha: &str, files: &[GeneratedFile]) -> Result<Vec<FileUpdate>, Error> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+    files.iter().map(|f| -> Result<FileUpdate, Error> {
+        let template = match &f.template {
+            TemplateSource::File(path) => std::fs::read_to_string(format!("{}/{}", repo_path, path))
+                .map_err(|e| Error::VersionFileError{source: e, file: path.clone()})?,
+            TemplateSource::Inline(inline) => inline.clone(),
+        };
+        let before = match std::fs::read_to_string(format!("{}/{}", repo_path, f.relative_path)) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(Error::VersionFileError{source: e, file: f.relative_path.clone()}),
+        };
+        let after = template::render(&template, version, sha, &date)
+            .map_err(|e| Error::TemplateError{source: e, file: f.relative_path.clone()})?;
+        Ok(FileUpdate{relative_path: f.relative_path.clone(), before, after})
+    }).collect()
+}
+
 /// Compiles the provided prefix and postfix into a Regex with the SEMVER_MATCHER constant
 /// Example: `version_prefix: "version = \\""`, `version_postfix: "\\"[^,]"`
-/// Compiled: `(version = \\"){SEMVER_MATCHER}(\\"[^,])`
+/// Compiled: `(?P<conver_prefix>version = \\"){SEMVER_MATCHER}(?P<conver_postfix>\\"[^,])`
 /// Matches: `version = "2.12.18"`
+/// The groups are named, so groups in the prefix don't shift the postfix.
 fn construct_matcher(prefix: String, postfix: String) -> Result<regex::Regex, regex::Error> {
-    Ok(Regex::new(&format!("({}){}({})", prefix, SEMVER_MATCHER, postfix))?)
+    Regex::new(&format!("(?P<conver_prefix>{}){}(?P<conver_postfix>{})", prefix, SEMVER_MATCHER, postfix))
 }
 
-/// Update versions in various version files.
-/// package.josn, cargo.toml, etc.
-pub fn bump_version_files(repo_path: &str, version: &str, files: &Vec<VersionFile>) -> Vec<Error> {
+/// New contents computed for a version or changelog file.
+#[derive(Debug, Clone)]
+pub struct FileUpdate {
+    pub relative_path: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// Computes the updated contents of every version file, without writing them.
+/// Files that can't be read or don't contain a version are returned as errors.
+pub fn compute_version_files(repo_path: &str, version: &str, files: &[VersionFile]) -> (Vec<FileUpdate>, Vec<Error>) {
     let version = match version.strip_prefix("v") {
         Some(v) => v,
         None => version,
     };
 
-    files.iter().filter_map(|f| -> Option<Error> {
+    let mut updates: Vec<FileUpdate> = vec![];
+    let mut errors = vec![];
+    files.iter().for_each(|f| {
+        let file_version = f.file_version(version);
+        // Several entries may edit the same file, such as the [project] and [tool.poetry] versions of pyproject.toml.
+        if let Some(update) = updates.iter_mut().find(|u| u.relative_path == f.relative_path) {
+            match f.editor.replace(&update.after, &file_version) {
+                Ok(after) => update.after = after,
+                Err(e) => errors.push(f.edit_error(e)),
+            }
+            return;
+        }
+
         // Get file based on relative path
-        let str_pth = format!("{}/{}", repo_path, f.relative_path).to_string();
-        let pth = Path::new(&str_pth);
-        let contents = match std::fs::read_to_string(pth) {
+        let str_pth = format!("{}/{}", repo_path, f.relative_path);
+        let contents = match std::fs::read_to_string(&str_pth) {
             Ok(c) => c,
-            Err(e) => return Some(Error::VersionFileError{source: e, file: f.relative_path.clone()}),
+            Err(e) => return errors.push(Error::VersionFileError{source: e, file: f.relative_path.clone()}),
         };
 
-        // Scan file contents with matcher regex
-        let cap = match f.matcher.captures(&contents) {
-            Some(c) => c,
-            None => return Some(Error::VersionMatchError{file: f.relative_path.clone()}),
+        let after = match f.editor.replace(&contents, &file_version) {
+            Ok(after) => after,
+            Err(e) => return errors.push(f.edit_error(e)),
         };
+        updates.push(FileUpdate{
+            relative_path: f.relative_path.clone(),
+            before: contents,
+            after,
+        });
+    });
+    (updates, errors)
+}
 
-        let fmt_str = match f.v {
-            true => format!("{}v{}{}", cap[1].to_string(), version, cap[2].to_string()),
-            false => format!("{}{}{}", cap[1].to_string(), version, cap[2].to_string()),
-        };
-        let cow = f.matcher.replace_all(&contents, fmt_str);
+/// A version file that does not contain the version it was checked against.
+#[derive(Debug, Clone)]
+pub struct VersionMismatch {
+    pub relative_path: String,
+    /// The version read from the file, if it could be read.
+    pub found: Option<String>,
+    pub expected: String,
+    /// Every line that differs, such as a workspace requirement when `found` is the expected version.
+    pub lines: Vec<MismatchedLine>,
+}
 
-        // Don't write to file until files have been updated.
-        // Update file
-        match File::options().write(true).open(pth) {
-            Ok(mut out_file) => {
-                out_file.write_all(cow.as_ref().as_bytes()).err()?;
-            },
-            Err(e) => return Some(Error::VersionFileError{source: e, file: f.relative_path.clone()}),
+/// A line of a version file that bumping it would change.
+#[derive(Debug, Clone)]
+pub struct MismatchedLine {
+    /// 1-based line number.
+    pub line: usize,
+    pub found: String,
+    pub expected: String,
+}
+
+/// The lines that differ between the contents of a file before and after an update.
+fn mismatched_lines(before: &str, after: &str) -> Vec<MismatchedLine> {
+    let (before, after): (Vec<&str>, Vec<&str>) = (before.lines().collect(), after.lines().collect());
+    (0..before.len().max(after.len()))
+        .filter(|&i| before.get(i) != after.get(i))
+        .map(|i| MismatchedLine{
+            line: i + 1,
+            found: before.get(i).map_or("", |l| l.trim()).to_string(),
+            expected: after.get(i).map_or("", |l| l.trim()).to_string(),
+        })
+        .collect()
+}
+
+/// Compares every version file with `version`, a file matches when bumping it to `version` would not change it.
+/// Every occurrence and workspace requirement is compared, not only the version that is read.
+pub fn check_version_files(repo_path: &str, version: &str, files: &[VersionFile]) -> (Vec<VersionMismatch>, Vec<Error>) {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let mut mismatches = vec![];
+    let mut errors = vec![];
+    for f in files {
+        let (updates, errs) = compute_version_files(repo_path, version, std::slice::from_ref(f));
+        errors.extend(errs);
+        if let Some(u) = updates.iter().find(|u| u.before != u.after) {
+            mismatches.push(VersionMismatch{
+                relative_path: f.relative_path.clone(),
+                found: f.read_version(repo_path).ok(),
+                expected: f.file_version(version),
+                lines: mismatched_lines(&u.before, &u.after),
+            });
+        }
+    }
+    (mismatches, errors)
+}
+
+/// Computes the changelog, version and generated file updates of the release of `version`, without writing them.
+/// `sha`, HEAD before the release commit, is rendered into the generated files. Every version and generated file is computed,
+/// so all of their errors are returned together, the updates must not be written when any is returned.
+/// Files outside the repository, absolute or containing `..`, are errors.
+pub fn compute_release_files(repo: &ConventionalRepo, repo_path: &str, version: &str, sha: &str) -> anyhow::Result<(Vec<FileUpdate>, Vec<Error>)> {
+    let v_files = VersionFile::config_to_version_files(&repo.config, repo_path)?;
+    let c_files = ChangelogFile::config_to_changelog_files(&repo.config)?;
+    let g_files = GeneratedFile::config_to_generated_files(&repo.config)?;
+    let mut updates = vec![];
+    if !c_files.is_empty() {
+        let notes = repo.release_notes(version)?;
+        updates.extend(compute_changelog_files(repo_path, &notes, &c_files)?);
+    }
+    let (v_updates, mut errors) = compute_bumped_files(repo_path, version, &v_files, &g_files, sha);
+    updates.extend(v_updates);
+    errors.extend(outside_repo_errors(&updates));
+    Ok((updates, errors))
+}
+
+/// The version and generated file updates of `compute_release_files`.
+fn compute_bumped_files(repo_path: &str, version: &str, files: &[VersionFile], generated_files: &[GeneratedFile], sha: &str) -> (Vec<FileUpdate>, Vec<Error>) {
+    let (mut updates, mut errors) = compute_version_files(repo_path, version, files);
+    match compute_generated_files(repo_path, version, sha, generated_files) {
+        Ok(generated) => updates.extend(generated),
+        Err(e) => errors.push(e),
+    }
+    (updates, errors)
+}
+
+/// Update versions in various version files and render the generated files, `sha` is HEAD before the release commit.
+/// package.josn, cargo.toml, etc.
+/// Nothing is written unless every file could be bumped, and a failed write restores the files already written.
+pub fn bump_version_files(repo_path: &str, version: &str, files: &[VersionFile], generated_files: &[GeneratedFile], sha: &str) -> Vec<Error> {
+    let (updates, mut errors) = compute_bumped_files(repo_path, version, files, generated_files, sha);
+    errors.extend(outside_repo_errors(&updates));
+    if !errors.is_empty() {
+        return errors;
+    }
+    write_file_updates(repo_path, &updates).err().into_iter().collect()
+}
+
+fn outside_repo_errors(updates: &[FileUpdate]) -> Vec<Error> {
+    updates.iter()
+        .filter(|u| !is_inside_repo(&u.relative_path))
+        .map(|u| Error::ReleasePathError{file: u.relative_path.clone()})
+        .collect()
+}
+
+/// Whether a relative path stays inside the repository, it must not be absolute nor contain `..`.
+pub fn is_inside_repo(relative_path: &str) -> bool {
+    Path::new(relative_path).components()
+        .all(|c| matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir))
+}
+
+/// Writes every update through a temporary file renamed over the original.
+/// When a write fails, the files already written are restored and files created by the update removed.
+pub fn write_file_updates(repo_path: &str, updates: &[FileUpdate]) -> Result<(), Error> {
+    write_updates(repo_path, updates).map(|_| ())
+}
+
+/// Writes every update, commits them with `message` and tags the release commit as `tag`, if any.
+/// When the commit or tag fails, HEAD and the index are reset to the previous commit and every written file is restored.
+/// The repository must be clean, as its whole index is committed.
+pub fn release_file_updates(
+    repo: &ConventionalRepo,
+    repo_path: &str,
+    updates: &[FileUpdate],
+    message: &str,
+    tag: Option<&str>,
+) -> Result<(Oid, Option<Oid>), Error> {
+    let head = repo.repo.head()?.peel_to_commit()?;
+    let written = write_updates(repo_path, updates)?;
+    let paths: Vec<&str> = updates.iter().map(|u| u.relative_path.as_str()).collect();
+    let released = commit_release_files(repo, message, &paths)
+        .and_then(|commit| Ok((commit, tag.map(|t| tag_release(repo, t)).transpose()?)));
+    if released.is_err() {
+        if let Err(e) = repo.repo.reset(head.as_object(), git2::ResetType::Mixed, None) {
+            tracing::error!(error = %e, "failed to reset HEAD after a failed release");
+        }
+        rollback(repo_path, &written);
+    }
+    released
+}
+
+/// Writes every update, returning each written update and whether its file existed before.
+fn write_updates<'a>(repo_path: &str, updates: &'a [FileUpdate]) -> Result<Vec<(&'a FileUpdate, bool)>, Error> {
+    let mut written: Vec<(&FileUpdate, bool)> = vec![];
+    for u in updates {
+        let str_pth = format!("{}/{}", repo_path, u.relative_path);
+        let pth = Path::new(&str_pth);
+        let existed = pth.exists();
+        if let Err(e) = write_atomic(pth, &u.after) {
+            rollback(repo_path, &written);
+            return Err(Error::VersionFileError{source: e, file: u.relative_path.clone()});
+        }
+        written.push((u, existed));
+    }
+    Ok(written)
+}
+
+fn rollback(repo_path: &str, written: &[(&FileUpdate, bool)]) {
+    for (u, existed) in written.iter().rev() {
+        let str_pth = format!("{}/{}", repo_path, u.relative_path);
+        let restored = match existed {
+            true => write_atomic(Path::new(&str_pth), &u.before),
+            false => std::fs::remove_file(&str_pth),
+        };
+        match restored {
+            Ok(_) => tracing::info!(file = %u.relative_path, "restored release file"),
+            Err(e) => tracing::error!(file = %u.relative_path, error = %e, "failed to restore release file"),
         }
-        None
+    }
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`,
+/// so a file is never left partially written. The permissions of an existing file are kept.
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_file_name(format!(".{}.conver-tmp", name.to_string_lossy()));
+    let written = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| match std::fs::metadata(path) {
+            Ok(meta) => std::fs::set_permissions(&tmp, meta.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|_| std::fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Computes the updated contents of every changelog file, without writing them.
+pub fn compute_changelog_files(repo_path: &str, notes: &ReleaseNotes, files: &[ChangelogFile]) -> Result<Vec<FileUpdate>, Error> {
+    let tag = notes.version.as_str();
+    let version = match tag.strip_prefix('v') {
+        Some(v) => v,
+        None => tag,
+    };
+    let now = chrono::Local::now();
+    let date = now.format("%Y-%m-%d").to_string();
+
+    files.iter().map(|f| -> Result<FileUpdate, Error> {
+        let str_pth = format!("{}/{}", repo_path, f.relative_path);
+        let contents = match std::fs::read_to_string(&str_pth) {
+            Ok(c) => c,
+            // Packaging changelogs are created on their first release.
+            Err(e) if e.kind() == io::ErrorKind::NotFound && f.format != ChangelogFormat::KeepAChangelog => String::new(),
+            Err(e) => return Err(Error::VersionFileError{source: e, file: f.relative_path.clone()}),
+        };
+        let updated = match f.format {
+            ChangelogFormat::KeepAChangelog => changelog::release_unreleased(&contents, version, tag, &date),
+            ChangelogFormat::Debian => changelog::render_debian_entry(notes, &f.options, &now)
+                .and_then(|entry| changelog::prepend_debian(&contents, &entry, &f.options, version)),
+            ChangelogFormat::Rpm => Ok(changelog::insert_rpm(
+                &contents, &changelog::render_rpm_entry(notes, &f.options, &now))),
+        }.map_err(|e| Error::ChangelogError{source: e, file: f.relative_path.clone()})?;
+        Ok(FileUpdate{
+            relative_path: f.relative_path.clone(),
+            before: contents,
+            after: updated,
+        })
     }).collect()
 }
 
+/// Release the pending changes of every changelog file as the version of `notes`.
+/// All changelogs are validated before any of them is written,
+/// so a missing or malformed section leaves every file untouched.
+pub fn bump_changelog_files(repo_path: &str, notes: &ReleaseNotes, files: &[ChangelogFile]) -> Result<(), Error> {
+    let updates = compute_changelog_files(repo_path, notes, files)?;
+    write_file_updates(repo_path, &updates)
+}
+
+/// Signature of the release commits and tags, from the `commit_signature` config.
+pub fn release_signature(repo: &ConventionalRepo) -> Result<Signature<'static>, Error> {
+    Ok(Signature::now(
+        &repo.config.commit_signature.name,
+        &repo.config.commit_signature.email)?)
+}
+
+/// Message of the release commit created by `commit_version_files`.
+pub fn release_commit_message(version: &str) -> String {
+    format!("chore(release): created release {}", version)
+}
+
 /// Tag Head commit of Repository, with the provided version.
 pub fn tag_release(repo: &ConventionalRepo, version: &str) -> Result<Oid, Error> {
     // Tag the repository with a version
-    let sig = Signature::now(
-        &repo.config.commit_signature.name,
-        &repo.config.commit_signature.email)?;
+    let sig = release_signature(repo)?;
     let head = repo.repo.head()?.peel_to_commit()?;
-    Ok(repo.repo.tag(&version.to_string(), head.as_object(), &sig, "", false)?)
+    Ok(repo.repo.tag(version, head.as_object(), &sig, "", false)?)
 }
 
+/// Stages every version, changelog and generated file and commits them as the release of `version`.
 pub fn commit_version_files(
     repo: &ConventionalRepo,
     version: &str,
-    version_files: &Vec<VersionFile>
+    version_files: &[VersionFile],
+    changelog_files: &[ChangelogFile],
+    generated_files: &[GeneratedFile],
 ) -> Result<Oid, Error> {
-    let sig = Signature::now(
-        &repo.config.commit_signature.name,
-        &repo.config.commit_signature.email)?;
+    let paths: Vec<&str> = version_files.iter().map(|v| v.relative_path.as_str())
+        .chain(changelog_files.iter().map(|c| c.relative_path.as_str()))
+        .chain(generated_files.iter().map(|g| g.relative_path.as_str()))
+        .collect();
+    commit_release_files(repo, &release_commit_message(version), &paths)
+}
+
+/// Stages the files at the relative `paths` and commits them with `message`, nothing is committed when a file can't be staged.
+pub fn commit_release_files(repo: &ConventionalRepo, message: &str, paths: &[&str]) -> Result<Oid, Error> {
+    let sig = release_signature(repo)?;
 
     let head = repo.repo.head()?;
     let commit = head.peel_to_commit()?;
     let parent_commits: [&Commit; 1] = [&commit];
 
     let mut index = repo.repo.index()?;
-    version_files.iter().for_each(|v: &VersionFile| {
-        if let Err(e) = index.add_path(&Path::new(&v.relative_path)) {
-            eprintln!("Error Encountered {}", e);
-        }
-    });
+    for p in paths {
+        index.add_path(Path::new(p))
+            .map_err(|e| Error::StageError{source: e, file: p.to_string()})?;
+    }
     index.write()?;
 
     // Regrab index from repo, to prevent staging old changes.
@@ -144,9 +625,8 @@ pub fn commit_version_files(
         Some("HEAD"),
         &sig,
         &sig,
-        &format!("chore(release): created release {}", version).to_owned(),
+        message,
         &commit_tree,
         &parent_commits
     )?)
 }
-
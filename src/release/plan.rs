@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{Error, FileUpdate};
+use crate::{ConventionalRepo, VersionDetails};
+
+/// A reviewed release, recorded by `conver plan` and replayed by `conver apply`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReleasePlan {
+    /// Commit the plan was derived from, apply refuses to run on any other HEAD.
+    pub head: String,
+    pub base_tag: Option<String>,
+    pub version: String,
+    pub files: Vec<PlannedFile>,
+    pub actions: Vec<GitAction>,
+}
+
+/// An edit of a version or changelog file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlannedFile {
+    pub path: String,
+    pub before_sha256: String,
+    pub after_sha256: String,
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum GitAction {
+    Commit{message: String, signature: String, files: Vec<String>},
+    Tag{name: String, signature: String},
+}
+
+/// Hex encoded sha256 of file contents.
+pub fn content_hash(contents: &str) -> String {
+    format!("{:x}", Sha256::digest(contents.as_bytes()))
+}
+
+impl ReleasePlan {
+    /// Bumps every version and changelog file in memory and records
+    /// the edits, release commit and tag that `apply` will perform.
+    pub fn create(repo: &ConventionalRepo, repo_path: &str, details: &VersionDetails, version: &str) -> anyhow::Result<Self> {
+        if details.dirty {
+            return Err(Error::PlanError{reason: String::from("the repository is dirty")}.into());
+        }
+        if details.tagged {
+            return Err(Error::PlanError{reason: String::from("HEAD is already tagged")}.into());
+        }
+
+        let (updates, errors) = super::compute_release_files(repo, repo_path, version, &details.head.to_string())?;
+        if let Some(e) = errors.into_iter().next() {
+            return Err(e.into());
+        }
+
+        let signature = planned_signature(repo)?;
+        let files: Vec<PlannedFile> = updates.into_iter().map(|u| PlannedFile{
+            path: u.relative_path,
+            before_sha256: content_hash(&u.before),
+            after_sha256: content_hash(&u.after),
+            content: u.after,
+        }).collect();
+        let actions = vec![
+            GitAction::Commit{
+                message: super::release_commit_message(version),
+                signature: signature.clone(),
+                files: files.iter().map(|f| f.path.clone()).collect(),
+            },
+            GitAction::Tag{name: version.to_string(), signature},
+        ];
+        Ok(ReleasePlan{
+            head: details.head.to_string(),
+            base_tag: details.previous_version.clone(),
+            version: version.to_string(),
+            files,
+            actions,
+        })
+    }
+
+    /// Checks that the repository is clean and that HEAD, every planned file, the signature and the tags still match the plan,
+    /// then writes the files, commits and tags the release with the planned message and names.
+    /// The files are restored when the commit or tag fails.
+    pub fn apply(&self, repo: &ConventionalRepo, repo_path: &str) -> anyhow::Result<()> {
+        let head = repo.repo.head()?.peel_to_commit()?.id().to_string();
+        if head != self.head {
+            return Err(Error::PlanError{
+                reason: format!("HEAD is {}, but the plan was created for {}", head, self.head)
+            }.into());
+        }
+        if repo.is_repo_dirty()? {
+            return Err(Error::PlanError{reason: String::from("the repository is dirty")}.into());
+        }
+        let signature = planned_signature(repo)?;
+        for action in &self.actions {
+            let planned = match action {
+                GitAction::Commit{signature, ..} => signature,
+                GitAction::Tag{name, signature} => {
+                    if repo.repo.refname_to_id(&format!("refs/tags/{}", name)).is_ok() {
+                        return Err(Error::PlanError{reason: format!("the tag {} already exists", name)}.into());
+                    }
+                    signature
+                },
+            };
+            if *planned != signature {
+                return Err(Error::PlanError{
+                    reason: format!("the release signature is {}, but the plan was created for {}", signature, planned)
+                }.into());
+            }
+        }
+        let mut updates = vec![];
+        for f in &self.files {
+            if !super::is_inside_repo(&f.path) {
+                return Err(Error::ReleasePathError{file: f.path.clone()}.into());
+            }
+            let str_pth = format!("{}/{}", repo_path, f.path);
+            let contents = match std::fs::read_to_string(&str_pth) {
+                Ok(c) => c,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(Error::VersionFileError{source: e, file: f.path.clone()}.into()),
+            };
+            if content_hash(&contents) != f.before_sha256 {
+                return Err(Error::PlanError{
+                    reason: format!("{} changed since the plan was created", f.path)
+                }.into());
+            }
+            if content_hash(&f.content) != f.after_sha256 {
+                return Err(Error::PlanError{
+                    reason: format!("the planned content of {} does not match its hash", f.path)
+                }.into());
+            }
+            updates.push(FileUpdate{
+                relative_path: f.path.clone(),
+                before: contents,
+                after: f.content.clone(),
+            });
+        }
+
+        let mut message = None;
+        let mut tag = None;
+        for action in &self.actions {
+            match action {
+                GitAction::Commit{message: m, ..} => message = Some(m.as_str()),
+                GitAction::Tag{name, ..} => tag = Some(name.as_str()),
+            }
+        }
+        let message = message.ok_or_else(|| Error::PlanError{reason: String::from("the plan has no release commit")})?;
+        super::release_file_updates(repo, repo_path, &updates, message, tag)?;
+        Ok(())
+    }
+}
+
+/// The release signature as recorded in a plan, `name <email>`.
+fn planned_signature(repo: &ConventionalRepo) -> Result<String, Error> {
+    let sig = super::release_signature(repo)?;
+    Ok(format!("{} <{}>", sig.name().unwrap_or_default(), sig.email().unwrap_or_default()))
+}
@@ -0,0 +1,144 @@
+use std::str::FromStr;
+
+/// How a derived version is written into a version file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionScheme {
+    /// The derived semantic version, as is.
+    Semver,
+    /// A PEP 440 version, for Python packages.
+    Pep440,
+    /// A four part `major.minor.patch.revision` version, for .NET assembly versions.
+    Assembly,
+    /// A container image tag, build metadata joined with `_` instead of `+`.
+    DockerTag,
+}
+
+impl FromStr for VersionScheme {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<VersionScheme, crate::Error> {
+        match s {
+            "semver" => Ok(VersionScheme::Semver),
+            "pep440" => Ok(VersionScheme::Pep440),
+            "assembly" => Ok(VersionScheme::Assembly),
+            "docker-tag" => Ok(VersionScheme::DockerTag),
+            _ => Err(crate::Error::InvalidConfigError{
+                reason: format!("unsupported version scheme {}", s)
+            }),
+        }
+    }
+}
+
+impl VersionScheme {
+    /// Formats a semantic version, without any leading v, for this scheme.
+    pub fn format(&self, version: &str) -> String {
+        match self {
+            VersionScheme::Semver => version.to_string(),
+            VersionScheme::Pep440 => pep440(version),
+            VersionScheme::Assembly => assembly(version),
+            // Tags only allow letters, digits, `_`, `.` and `-`, Helm makes the same substitution.
+            VersionScheme::DockerTag => version.replace('+', "_"),
+        }
+    }
+}
+
+/// Drops the prerelease and build metadata of a semantic version, keeping the commit count
+/// of derived prereleases as the revision. `1.2.0-3+abc1234` is written as `1.2.0.3`, `1.2.0` as `1.2.0.0`.
+fn assembly(version: &str) -> String {
+    let version = version.split('+').next().unwrap_or(version);
+    let (release, pre) = match version.split_once('-') {
+        Some((r, p)) => (r, p),
+        None => (version, ""),
+    };
+    let revision = pre.rsplit('.').next()
+        .and_then(|n| n.parse::<u16>().ok())
+        .unwrap_or(0);
+    format!("{}.{}", release, revision)
+}
+
+/// Normalises a semantic version to PEP 440.
+/// `alpha`, `beta` and `rc` prereleases map to `a`, `b` and `rc`, the commit count of
+/// derived prereleases to a dev release, and build metadata to a local version label.
+/// `1.2.0-3+abc1234` is written as `1.2.0.dev3+abc1234`.
+fn pep440(version: &str) -> String {
+    let (version, local) = match version.split_once('+') {
+        Some((v, l)) => (v, Some(l)),
+        None => (version, None),
+    };
+    let (release, pre) = match version.split_once('-') {
+        Some((r, p)) => (r, Some(p)),
+        None => (version, None),
+    };
+
+    let mut out = release.to_string();
+    let mut local: Vec<String> = local.map(local_segments).unwrap_or_default();
+    if let Some(pre) = pre {
+        let (label, number) = match pre.split_once('.') {
+            Some((label, number)) if number.chars().all(|c| c.is_ascii_digit()) => (label, number),
+            _ if pre.chars().all(|c| c.is_ascii_digit()) => ("", pre),
+            _ => (pre, ""),
+        };
+        let number = number.parse::<u64>().unwrap_or(0);
+        match label.to_ascii_lowercase().as_str() {
+            "" | "dev" => out.push_str(&format!(".dev{}", number)),
+            "a" | "alpha" => out.push_str(&format!("a{}", number)),
+            "b" | "beta" => out.push_str(&format!("b{}", number)),
+            "rc" | "c" | "pre" | "preview" => out.push_str(&format!("rc{}", number)),
+            "post" => out.push_str(&format!(".post{}", number)),
+            _ => {
+                // Unknown labels are kept in the local version label.
+                out.push_str(".dev0");
+                local.splice(0..0, local_segments(pre));
+            },
+        }
+    }
+    if !local.is_empty() {
+        out.push('+');
+        out.push_str(&local.join("."));
+    }
+    out
+}
+
+/// Splits a label into PEP 440 local version segments, only ASCII letters and digits are allowed.
+fn local_segments(label: &str) -> Vec<String> {
+    label.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pep440_maps_prereleases_and_build_metadata() {
+        let cases = [
+            ("1.2.0", "1.2.0"),
+            ("1.2.0-3", "1.2.0.dev3"),
+            ("1.2.0-3+abc1234", "1.2.0.dev3+abc1234"),
+            ("1.2.0-alpha.1", "1.2.0a1"),
+            ("1.2.0-beta.2", "1.2.0b2"),
+            ("1.2.0-rc.1", "1.2.0rc1"),
+            ("1.2.0-RC.1", "1.2.0rc1"),
+            ("1.2.0-post.1", "1.2.0.post1"),
+            ("1.2.0-nightly", "1.2.0.dev0+nightly"),
+            ("1.2.0+Build_7", "1.2.0+build.7"),
+        ];
+        for (semver, expected) in cases {
+            assert_eq!(VersionScheme::Pep440.format(semver), expected, "{}", semver);
+        }
+    }
+
+    #[test]
+    fn assembly_keeps_the_commit_count_as_revision() {
+        assert_eq!(VersionScheme::Assembly.format("1.2.0"), "1.2.0.0");
+        assert_eq!(VersionScheme::Assembly.format("1.2.0-3+abc1234"), "1.2.0.3");
+        assert_eq!(VersionScheme::Assembly.format("1.2.0-rc.2"), "1.2.0.2");
+        assert_eq!(VersionScheme::Assembly.format("1.2.0-rc"), "1.2.0.0");
+    }
+
+    #[test]
+    fn docker_tag_replaces_build_separator() {
+        assert_eq!(VersionScheme::DockerTag.format("1.2.0-3+abc1234"), "1.2.0-3_abc1234");
+    }
+}
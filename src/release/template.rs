@@ -0,0 +1,20 @@
+use semver::Version;
+
+/// Renders a generated file template for `version`, without any leading v.
+/// `{version}`, `{major}`, `{minor}`, `{patch}`, `{prerelease}`, `{build}`, `{sha}`, `{short_sha}` and `{date}`
+/// are replaced, any other brace is kept, so JSON and C templates need no escaping.
+pub fn render(template: &str, version: &str, sha: &str, date: &str) -> Result<String, semver::Error> {
+    let parsed = Version::parse(version)?;
+    let placeholders = [
+        ("{version}", version.to_string()),
+        ("{major}", parsed.major.to_string()),
+        ("{minor}", parsed.minor.to_string()),
+        ("{patch}", parsed.patch.to_string()),
+        ("{prerelease}", parsed.pre.to_string()),
+        ("{build}", parsed.build.to_string()),
+        ("{sha}", sha.to_string()),
+        ("{short_sha}", sha.chars().take(7).collect()),
+        ("{date}", date.to_string()),
+    ];
+    Ok(placeholders.iter().fold(template.to_string(), |out, (placeholder, value)| out.replace(placeholder, value)))
+}
@@ -0,0 +1,232 @@
+use std::path::Path;
+
+use toml_edit::{DocumentMut, Item, Value};
+
+use super::super::editor::{parse_toml as parse, replace_toml_str as replace_str, EditError};
+use super::super::Error;
+use super::{join_path, requirement};
+
+static DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Manifests and package names of a Cargo workspace.
+#[derive(Debug)]
+pub struct CargoWorkspace {
+    /// Relative paths of the root manifest followed by every member manifest.
+    pub manifests: Vec<String>,
+    /// Package names of the root package and every member.
+    pub members: Vec<String>,
+    /// Relative path of Cargo.lock, if it exists.
+    pub lockfile: Option<String>,
+}
+
+/// Discovers the members of the workspace whose root manifest is in `root`,
+/// relative to `repo_path`, from its `members` globs and `exclude` list.
+pub fn discover(repo_path: &str, root: &str) -> Result<CargoWorkspace, Error> {
+    let root_manifest = join_path(root, "Cargo.toml");
+    let doc = read_manifest(repo_path, &root_manifest)?;
+    let workspace = doc.get("workspace").ok_or_else(|| Error::WorkspaceError{
+        reason: format!("{} has no [workspace] table", root_manifest),
+    })?;
+    let strings = |key: &str| -> Vec<String> {
+        workspace.get(key)
+            .and_then(Item::as_array)
+            .map(|a| a.iter().filter_map(Value::as_str).map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    let exclude = strings("exclude");
+
+    let mut manifests = vec![root_manifest.clone()];
+    let mut members = vec![];
+    if let Some(name) = package_name(&doc) {
+        members.push(name);
+    }
+    let base = Path::new(repo_path).join(root).canonicalize()
+        .map_err(|e| Error::VersionFileError{source: e, file: root.to_string()})?;
+    for pattern in strings("members") {
+        let full = base.join(&pattern);
+        let paths = glob::glob(&full.to_string_lossy()).map_err(|e| Error::WorkspaceError{
+            reason: format!("invalid member pattern {}: {}", pattern, e),
+        })?;
+        for dir in paths.flatten() {
+            let member = match dir.strip_prefix(&base) {
+                Ok(m) => m.to_string_lossy().to_string(),
+                Err(_) => continue,
+            };
+            let manifest = join_path(root, &format!("{}/Cargo.toml", member));
+            if exclude.contains(&member) || manifests.contains(&manifest) || !dir.join("Cargo.toml").is_file() {
+                continue;
+            }
+            let member_doc = read_manifest(repo_path, &manifest)?;
+            if let Some(name) = package_name(&member_doc) {
+                members.push(name);
+            }
+            tracing::debug!(manifest = %manifest, "discovered cargo workspace member");
+            manifests.push(manifest);
+        }
+    }
+
+    let lockfile = join_path(root, "Cargo.lock");
+    Ok(CargoWorkspace{
+        manifests,
+        members,
+        lockfile: Path::new(repo_path).join(&lockfile).is_file().then_some(lockfile),
+    })
+}
+
+fn read_manifest(repo_path: &str, manifest: &str) -> Result<DocumentMut, Error> {
+    let contents = std::fs::read_to_string(Path::new(repo_path).join(manifest))
+        .map_err(|e| Error::VersionFileError{source: e, file: manifest.to_string()})?;
+    parse(&contents).map_err(|e| Error::VersionEditError{source: e, file: manifest.to_string()})
+}
+
+fn package_name(doc: &DocumentMut) -> Option<String> {
+    doc.get("package")?.get("name")?.as_str().map(str::to_string)
+}
+
+/// Reads `package.version`, or `workspace.package.version` for inherited versions.
+pub fn read_manifest_version(contents: &str) -> Result<String, EditError> {
+    let doc = parse(contents)?;
+    let version = [doc.get("package"), doc.get("workspace").and_then(|w| w.get("package"))].into_iter()
+        .flatten()
+        .find_map(|p| p.get("version")?.as_str().map(str::to_string));
+    version.ok_or(EditError::MissingVersion)
+}
+
+/// Bumps `package.version` and `workspace.package.version`, unless inherited,
+/// and the version requirements on every workspace member.
+pub fn bump_manifest(contents: &str, version: &str, members: &[String]) -> Result<String, EditError> {
+    let mut doc = parse(contents)?;
+    if let Some(package) = doc.get_mut("package") {
+        set_version(package, version);
+    }
+
+    let mut tables: Vec<&mut Item> = vec![];
+    let root = doc.as_table_mut();
+    for (key, item) in root.iter_mut() {
+        match key.get() {
+            "workspace" => {
+                if let Some(package) = item.get_mut("package") {
+                    set_version(package, version);
+                }
+                if let Some(deps) = item.get_mut("dependencies") {
+                    tables.push(deps);
+                }
+            },
+            "target" => {
+                if let Some(targets) = item.as_table_like_mut() {
+                    for (_, target) in targets.iter_mut() {
+                        if let Some(target) = target.as_table_like_mut() {
+                            tables.extend(target.iter_mut()
+                                .filter(|(k, _)| DEPENDENCY_TABLES.contains(&k.get()))
+                                .map(|(_, deps)| deps));
+                        }
+                    }
+                }
+            },
+            k if DEPENDENCY_TABLES.contains(&k) => tables.push(item),
+            _ => (),
+        }
+    }
+
+    for deps in tables {
+        let deps = match deps.as_table_like_mut() {
+            Some(deps) => deps,
+            None => continue,
+        };
+        for (name, dep) in deps.iter_mut() {
+            let package = dep.get("package").and_then(Item::as_str).unwrap_or(name.get()).to_string();
+            if !members.contains(&package) {
+                continue;
+            }
+            if let Some(Item::Value(req)) = dep.get_mut("version") {
+                if let Some(new) = req.as_str().and_then(|old| requirement(old, version)) {
+                    replace_str(req, &new);
+                }
+            }
+        }
+    }
+    Ok(doc.to_string())
+}
+
+/// Reads the version of the first workspace member recorded in Cargo.lock.
+pub fn read_lock_version(contents: &str, members: &[String]) -> Result<String, EditError> {
+    let doc = parse(contents)?;
+    doc.get("package")
+        .and_then(Item::as_array_of_tables)
+        .and_then(|packages| packages.iter().find(|p| is_member_entry(p, members)))
+        .and_then(|p| p.get("version")?.as_str().map(str::to_string))
+        .ok_or(EditError::MissingVersion)
+}
+
+/// Bumps the version of every workspace member recorded in Cargo.lock.
+/// Registry and git packages of the same name are left alone.
+pub fn bump_lock(contents: &str, version: &str, members: &[String]) -> Result<String, EditError> {
+    let mut doc = parse(contents)?;
+    let packages = doc.get_mut("package")
+        .and_then(Item::as_array_of_tables_mut)
+        .ok_or(EditError::MissingVersion)?;
+    for package in packages.iter_mut() {
+        if is_member_entry(package, members) {
+            if let Some(Item::Value(v)) = package.get_mut("version") {
+                replace_str(v, version);
+            }
+        }
+        // Dependencies are recorded as `name version` when several versions of a name are locked.
+        let dependencies = package.get_mut("dependencies").and_then(Item::as_array_mut);
+        for dep in dependencies.into_iter().flat_map(|d| d.iter_mut()) {
+            let pinned = match dep.as_str().map(|d| d.split(' ').collect::<Vec<&str>>()) {
+                Some(parts) if parts.len() == 2 && members.iter().any(|m| m == parts[0]) => {
+                    format!("{} {}", parts[0], version)
+                },
+                _ => continue,
+            };
+            replace_str(dep, &pinned);
+        }
+    }
+    Ok(doc.to_string())
+}
+
+fn is_member_entry(package: &toml_edit::Table, members: &[String]) -> bool {
+    !package.contains_key("source")
+        && package.get("name").and_then(Item::as_str).is_some_and(|n| members.iter().any(|m| m == n))
+}
+
+/// Sets the `version` of a package table, if it is not inherited from the workspace.
+fn set_version(package: &mut Item, version: &str) {
+    if let Some(Item::Value(v)) = package.get_mut("version") {
+        if v.is_str() {
+            replace_str(v, version);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bump_manifest_keeps_wildcard_requirements() {
+        let contents = "\
+[package]
+name = \"b\"
+version = \"1.2.0\" # bumped
+
+[dependencies]
+a = { path = \"../a\", version = \"1.*\" }
+c = { path = \"../c\", version = \"=1.2.0\" }
+serde = \"1.2.0\"
+";
+        let members = vec![String::from("a"), String::from("b"), String::from("c")];
+        assert_eq!(bump_manifest(contents, "1.3.0", &members).unwrap(), "\
+[package]
+name = \"b\"
+version = \"1.3.0\" # bumped
+
+[dependencies]
+a = { path = \"../a\", version = \"1.*\" }
+c = { path = \"../c\", version = \"=1.3.0\" }
+serde = \"1.2.0\"
+");
+    }
+}
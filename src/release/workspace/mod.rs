@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use super::editor::VersionEditor;
+use super::scheme::VersionScheme;
+use super::VersionFile;
+use crate::config::ConventionalSemverConfig;
+
+pub mod cargo;
+pub mod npm;
+
+/// Package ecosystems whose workspaces can be bumped together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+}
+
+impl FromStr for Ecosystem {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<Ecosystem, crate::Error> {
+        match s {
+            "cargo" => Ok(Ecosystem::Cargo),
+            "npm" => Ok(Ecosystem::Npm),
+            _ => Err(crate::Error::InvalidConfigError{
+                reason: format!("unsupported workspace ecosystem {}", s)
+            }),
+        }
+    }
+}
+
+/// Discovers the manifests and lockfiles of every configured workspace,
+/// as version files of the repository at `repo_path`.
+pub fn config_to_workspace_files(config: &ConventionalSemverConfig, repo_path: &str) -> anyhow::Result<Vec<VersionFile>> {
+    let mut files = vec![];
+    for workspace in config.workspaces.iter().flatten() {
+        match Ecosystem::from_str(&workspace.ecosystem)? {
+            Ecosystem::Cargo => {
+                let found = cargo::discover(repo_path, &workspace.path)?;
+                let manifests = found.manifests.into_iter()
+                    .map(|m| (m, VersionEditor::CargoManifest(found.members.clone())));
+                let lockfile = found.lockfile
+                    .map(|l| (l, VersionEditor::CargoLock(found.members.clone())));
+                files.extend(manifests.chain(lockfile).map(|(relative_path, editor)| VersionFile{
+                    relative_path,
+                    editor,
+                    v: false,
+                    scheme: VersionScheme::Semver,
+                }));
+            },
+            Ecosystem::Npm => {
+                let found = npm::discover(repo_path, &workspace.path)?;
+                if let Some(lock) = &found.yarn_berry_lock {
+                    tracing::warn!("{} is not updated, run `yarn install` after the release to refresh its workspace entries", lock);
+                }
+                let manifests = found.manifests.into_iter()
+                    .map(|m| (m, VersionEditor::NpmManifest(found.members.clone())));
+                let package_lock = found.package_lock
+                    .map(|l| (l, VersionEditor::NpmLock{
+                        member_dirs: found.member_dirs.clone(),
+                        members: found.members.clone(),
+                    }));
+                let pnpm_lock = found.pnpm_lock
+                    .map(|l| (l, VersionEditor::PnpmLock(found.members.clone())));
+                files.extend(manifests.chain(package_lock).chain(pnpm_lock).map(|(relative_path, editor)| VersionFile{
+                    relative_path,
+                    editor,
+                    v: false,
+                    scheme: VersionScheme::Semver,
+                }));
+            },
+        }
+    }
+    Ok(files)
+}
+
+/// Joins a file name onto a directory relative to the repository.
+fn join_path(dir: &str, file: &str) -> String {
+    match dir.trim_end_matches('/') {
+        "" | "." => file.to_string(),
+        dir => format!("{}/{}", dir.trim_start_matches("./"), file),
+    }
+}
+
+/// The requirement `old` with its operator, such as `=`, `^` or `workspace:^`, kept and its version replaced.
+/// Partial versions, x-ranges and wildcards such as `^1.2`, `1.x` or `1.*` keep their shape, only their numbers are replaced.
+/// Requirements without a version, such as `*`, are None. So are compound requirements such as
+/// `>=1.0.0, <2.0.0`, `>=1 <2` or `^1 || ^2`, they are left unchanged with a warning.
+fn requirement(old: &str, version: &str) -> Option<String> {
+    let start = old.find(|c: char| c.is_ascii_digit())?;
+    let (operator, old_version) = old.trim_end().split_at(start);
+    if old_version.contains([' ', ',', '|']) {
+        tracing::warn!(requirement = old, "compound version requirement on a workspace member is left unchanged");
+        return None;
+    }
+    // Build metadata is not allowed in requirements.
+    let version = version.split('+').next().unwrap_or(version);
+    let parts: Vec<&str> = old_version.split('.').collect();
+    let is_number = |p: &str| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit());
+    if parts.len() >= 3 && parts[..2].iter().all(|p| is_number(p)) && !is_wildcard(parts[2]) {
+        return Some(format!("{}{}", operator, version));
+    }
+    if parts.len() > 3 || !parts.iter().all(|p| is_number(p) || is_wildcard(p)) {
+        return None;
+    }
+    let numbers = version.split(['-', '.']);
+    let shaped: Vec<&str> = parts.iter().zip(numbers)
+        .map(|(old, new)| if is_wildcard(old) { *old } else { new })
+        .collect();
+    Some(format!("{}{}", operator, shaped.join(".")))
+}
+
+fn is_wildcard(part: &str) -> bool {
+    matches!(part, "x" | "X" | "*")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirement_keeps_the_operator() {
+        assert_eq!(requirement("1.2.0", "1.3.0").as_deref(), Some("1.3.0"));
+        assert_eq!(requirement("^1.2.0", "1.3.0").as_deref(), Some("^1.3.0"));
+        assert_eq!(requirement("=1.2.0", "1.3.0-2+abc1234").as_deref(), Some("=1.3.0-2"));
+        assert_eq!(requirement("workspace:~1.2.0", "1.3.0").as_deref(), Some("workspace:~1.3.0"));
+        assert_eq!(requirement("^1.2.0-rc.1", "1.3.0").as_deref(), Some("^1.3.0"));
+    }
+
+    #[test]
+    fn requirement_keeps_the_shape_of_partial_versions() {
+        assert_eq!(requirement("1.x", "1.3.0").as_deref(), Some("1.x"));
+        assert_eq!(requirement("1.x", "2.0.0").as_deref(), Some("2.x"));
+        assert_eq!(requirement("1.*", "2.0.0").as_deref(), Some("2.*"));
+        assert_eq!(requirement("1.2.x", "1.3.0").as_deref(), Some("1.3.x"));
+        assert_eq!(requirement("^1.2", "1.3.0-2+abc1234").as_deref(), Some("^1.3"));
+        assert_eq!(requirement("~1", "2.0.0").as_deref(), Some("~2"));
+    }
+
+    #[test]
+    fn requirement_leaves_other_ranges_unchanged() {
+        assert_eq!(requirement("*", "1.3.0"), None);
+        assert_eq!(requirement("workspace:^", "1.3.0"), None);
+        assert_eq!(requirement(">=1.0.0, <2.0.0", "1.3.0"), None);
+        assert_eq!(requirement(">=1 <2", "1.3.0"), None);
+        assert_eq!(requirement("^1 || ^2", "1.3.0"), None);
+        assert_eq!(requirement("1.x.3.4", "1.3.0"), None);
+    }
+}
@@ -0,0 +1,261 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::super::editor::{replace_json_string, EditError};
+use super::super::Error;
+use super::{join_path, requirement};
+
+static DEPENDENCY_TABLES: [&str; 4] = ["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"];
+
+/// Manifests, package names and lockfiles of an npm or pnpm workspace.
+#[derive(Debug)]
+pub struct NpmWorkspace {
+    /// Relative paths of the root package.json followed by every workspace package.json.
+    pub manifests: Vec<String>,
+    /// Package names of the root package and every workspace package.
+    pub members: Vec<String>,
+    /// Directories of the workspace packages, relative to the root.
+    pub member_dirs: Vec<String>,
+    /// Relative path of package-lock.json, if it exists.
+    pub package_lock: Option<String>,
+    /// Relative path of pnpm-lock.yaml, if it exists.
+    pub pnpm_lock: Option<String>,
+    /// Relative path of a Yarn Berry yarn.lock, if it exists. Its workspace entries are not updated.
+    pub yarn_berry_lock: Option<String>,
+}
+
+/// Discovers the packages of the workspace whose root package.json is in `root`,
+/// relative to `repo_path`, from its `workspaces` globs or pnpm-workspace.yaml.
+pub fn discover(repo_path: &str, root: &str) -> Result<NpmWorkspace, Error> {
+    let root_manifest = join_path(root, "package.json");
+    let doc = read_manifest(repo_path, &root_manifest)?;
+    let mut patterns: Vec<String> = match doc.get("workspaces") {
+        Some(Value::Array(globs)) => strings(globs),
+        Some(Value::Object(w)) => w.get("packages").and_then(Value::as_array).map(|g| strings(g)).unwrap_or_default(),
+        _ => vec![],
+    };
+    let pnpm_workspace = join_path(root, "pnpm-workspace.yaml");
+    if let Ok(contents) = std::fs::read_to_string(Path::new(repo_path).join(&pnpm_workspace)) {
+        patterns.extend(pnpm_packages(&contents));
+    }
+    let (exclude, include): (Vec<String>, Vec<String>) = patterns.into_iter().partition(|p| p.starts_with('!'));
+    let exclude: Vec<glob::Pattern> = exclude.iter()
+        .filter_map(|p| glob::Pattern::new(p.trim_start_matches('!')).ok())
+        .collect();
+
+    let mut manifests = vec![root_manifest.clone()];
+    let mut members: Vec<String> = doc.get("name").and_then(Value::as_str).map(str::to_string).into_iter().collect();
+    let mut member_dirs = vec![];
+    let base = Path::new(repo_path).join(root).canonicalize()
+        .map_err(|e| Error::VersionFileError{source: e, file: root.to_string()})?;
+    for pattern in include {
+        let full = base.join(pattern.trim_end_matches('/'));
+        let paths = glob::glob(&full.to_string_lossy()).map_err(|e| Error::WorkspaceError{
+            reason: format!("invalid workspace pattern {}: {}", pattern, e),
+        })?;
+        for dir in paths.flatten() {
+            let member = match dir.strip_prefix(&base) {
+                Ok(m) => m.to_string_lossy().to_string(),
+                Err(_) => continue,
+            };
+            let manifest = join_path(root, &format!("{}/package.json", member));
+            if exclude.iter().any(|e| e.matches(&member)) || manifests.contains(&manifest)
+                || !dir.join("package.json").is_file() {
+                continue;
+            }
+            let member_doc = read_manifest(repo_path, &manifest)?;
+            if let Some(name) = member_doc.get("name").and_then(Value::as_str) {
+                members.push(name.to_string());
+            }
+            tracing::debug!(manifest = %manifest, "discovered npm workspace package");
+            manifests.push(manifest);
+            member_dirs.push(member);
+        }
+    }
+
+    let existing = |file: &str| Some(join_path(root, file))
+        .filter(|f| Path::new(repo_path).join(f).is_file());
+    Ok(NpmWorkspace{
+        manifests,
+        members,
+        member_dirs,
+        package_lock: existing("package-lock.json"),
+        pnpm_lock: existing("pnpm-lock.yaml"),
+        // Yarn classic lockfiles have no workspace entries, Berry lockfiles start with `__metadata:`.
+        yarn_berry_lock: existing("yarn.lock").filter(|l| {
+            std::fs::read_to_string(Path::new(repo_path).join(l)).is_ok_and(|c| c.contains("__metadata:"))
+        }),
+    })
+}
+
+fn read_manifest(repo_path: &str, manifest: &str) -> Result<Value, Error> {
+    let contents = std::fs::read_to_string(Path::new(repo_path).join(manifest))
+        .map_err(|e| Error::VersionFileError{source: e, file: manifest.to_string()})?;
+    serde_json::from_str(&contents).map_err(|e| Error::VersionEditError{
+        source: EditError::ParseError{reason: e.to_string()},
+        file: manifest.to_string(),
+    })
+}
+
+fn strings(values: &[Value]) -> Vec<String> {
+    values.iter().filter_map(Value::as_str).map(str::to_string).collect()
+}
+
+/// Entries of the `packages` list of a pnpm-workspace.yaml.
+fn pnpm_packages(contents: &str) -> Vec<String> {
+    let mut packages = vec![];
+    let mut in_packages = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if !line.starts_with(' ') && !line.starts_with('-') && !trimmed.is_empty() {
+            in_packages = trimmed == "packages:";
+        } else if let Some(entry) = trimmed.strip_prefix('-').filter(|_| in_packages) {
+            packages.push(unquote(entry.split(" #").next().unwrap_or_default()).to_string());
+        }
+    }
+    packages
+}
+
+fn unquote(s: &str) -> &str {
+    s.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// Bumps `version` and the dependency ranges on every workspace package.
+/// Ranges without a version, such as `*` or `workspace:^`, are left alone.
+pub fn bump_manifest(contents: &str, version: &str, members: &[String]) -> Result<String, EditError> {
+    let doc: Value = serde_json::from_str(contents)
+        .map_err(|e| EditError::ParseError{reason: e.to_string()})?;
+    let mut contents = contents.to_string();
+    if doc.get("version").is_some_and(Value::is_string) {
+        contents = replace_json_string(&contents, &["version"], version)?;
+    }
+    for table in DEPENDENCY_TABLES {
+        let deps = match doc.get(table).and_then(Value::as_object) {
+            Some(deps) => deps,
+            None => continue,
+        };
+        for (name, range) in deps.iter().filter(|(name, _)| members.contains(name)) {
+            if let Some(new) = range.as_str().and_then(|r| requirement(r, version)) {
+                contents = replace_json_string(&contents, &[table, name], &new)?;
+            }
+        }
+    }
+    Ok(contents)
+}
+
+/// Bumps the root `version`, `packages[""].version` and the version and dependency ranges
+/// of every workspace package directory recorded in package-lock.json.
+pub fn bump_package_lock(contents: &str, version: &str, member_dirs: &[String], members: &[String]) -> Result<String, EditError> {
+    let doc: Value = serde_json::from_str(contents)
+        .map_err(|e| EditError::ParseError{reason: e.to_string()})?;
+    let mut contents = contents.to_string();
+    if doc.get("version").is_some_and(Value::is_string) {
+        contents = replace_json_string(&contents, &["version"], version)?;
+    }
+    let packages = doc.get("packages").and_then(Value::as_object);
+    for dir in std::iter::once("").chain(member_dirs.iter().map(String::as_str)) {
+        let package = match packages.and_then(|p| p.get(dir)) {
+            Some(package) => package,
+            None => continue,
+        };
+        if package.get("version").is_some_and(Value::is_string) {
+            contents = replace_json_string(&contents, &["packages", dir, "version"], version)?;
+        }
+        for table in DEPENDENCY_TABLES {
+            let deps = package.get(table).and_then(Value::as_object).into_iter().flatten();
+            for (name, range) in deps.filter(|(name, _)| members.contains(name)) {
+                if let Some(new) = range.as_str().and_then(|r| requirement(r, version)) {
+                    contents = replace_json_string(&contents, &["packages", dir, table, name], &new)?;
+                }
+            }
+        }
+    }
+    Ok(contents)
+}
+
+/// Bumps the `specifier` of every dependency on a workspace package in pnpm-lock.yaml.
+pub fn bump_pnpm_lock(contents: &str, version: &str, members: &[String]) -> String {
+    let mut dependency: Option<String> = None;
+    let mut lines: Vec<String> = vec![];
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let bumped = match trimmed.split_once(':') {
+            // `my-core:` opens the entry of a dependency, its specifier follows.
+            Some((key, "")) => {
+                dependency = Some(unquote(key).to_string());
+                None
+            },
+            Some(("specifier", spec)) if dependency.as_ref().is_some_and(|d| members.contains(d)) => {
+                let quote = &spec.trim()[..spec.trim().len() - spec.trim().trim_start_matches(['"', '\'']).len()];
+                requirement(unquote(spec), version)
+                    .map(|new| format!("{}specifier: {}{}{}", indent, quote, new, quote))
+            },
+            _ => None,
+        };
+        match bumped {
+            Some(bumped) => lines.push(bumped + &line[line.trim_end().len()..]),
+            None => lines.push(line.to_string()),
+        }
+    }
+    lines.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members() -> Vec<String> {
+        vec![String::from("a"), String::from("b")]
+    }
+
+    #[test]
+    fn bump_manifest_bumps_the_version_and_member_ranges() {
+        let contents = r#"{
+  "name": "b",
+  "version": "1.2.0",
+  "dependencies": {"a": "^1.2.0", "left-pad": "^1.2.0"},
+  "devDependencies": {"a": "1.x"},
+  "peerDependencies": {"a": "*"}
+}
+"#;
+        assert_eq!(bump_manifest(contents, "1.3.0", &members()).unwrap(), r#"{
+  "name": "b",
+  "version": "1.3.0",
+  "dependencies": {"a": "^1.3.0", "left-pad": "^1.2.0"},
+  "devDependencies": {"a": "1.x"},
+  "peerDependencies": {"a": "*"}
+}
+"#);
+    }
+
+    #[test]
+    fn bump_package_lock_bumps_the_root_and_member_entries() {
+        let contents = r#"{
+  "name": "root",
+  "version": "1.2.0",
+  "packages": {
+    "": {"name": "root", "version": "1.2.0"},
+    "packages/b": {"name": "b", "version": "1.2.0", "dependencies": {"a": "~1.2.0"}},
+    "packages/c": {"name": "c", "dependencies": {"a": "1.x"}},
+    "node_modules/a": {"resolved": "packages/a", "link": true},
+    "node_modules/left-pad": {"version": "1.2.0"}
+  }
+}
+"#;
+        let dirs = vec![String::from("packages/b"), String::from("packages/c")];
+        assert_eq!(bump_package_lock(contents, "2.0.0", &dirs, &members()).unwrap(), r#"{
+  "name": "root",
+  "version": "2.0.0",
+  "packages": {
+    "": {"name": "root", "version": "2.0.0"},
+    "packages/b": {"name": "b", "version": "2.0.0", "dependencies": {"a": "~2.0.0"}},
+    "packages/c": {"name": "c", "dependencies": {"a": "2.x"}},
+    "node_modules/a": {"resolved": "packages/a", "link": true},
+    "node_modules/left-pad": {"version": "1.2.0"}
+  }
+}
+"#);
+    }
+}